@@ -0,0 +1,277 @@
+//! Gera os Packet IDs, structs de pacotes e o registro de versões a partir
+//! das descrições do protocolo
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Diretório com uma descrição por versão do protocolo (`<versão>.json`)
+const PROTOCOL_DIR: &str = "protocol";
+
+#[derive(Deserialize)]
+struct Protocol {
+    version: String,
+    hash: String,
+    packets: Vec<PacketDef>,
+}
+
+#[derive(Deserialize)]
+struct PacketDef {
+    id: u32,
+    name: String,
+    #[serde(default)]
+    doc: Option<String>,
+    /// Pacote com codec escrito à mão em packets.rs
+    #[serde(default)]
+    manual: bool,
+    /// Payload vai comprimido com zstd
+    #[serde(default)]
+    compressed: bool,
+    /// Sem campos descritos: só gera o ID e o nome
+    #[serde(default)]
+    fields: Option<Vec<FieldDef>>,
+}
+
+#[derive(Deserialize, PartialEq)]
+struct FieldDef {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    nullable: bool,
+    #[serde(default)]
+    array: bool,
+    #[serde(default)]
+    fixed: Option<Fixed>,
+}
+
+/// `"fixed": true` força FixedCodec, `"fixed": N` é string ASCII de N bytes
+#[derive(Deserialize, PartialEq)]
+#[serde(untagged)]
+enum Fixed {
+    Codec(bool),
+    Ascii(usize),
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", PROTOCOL_DIR);
+
+    let mut protocols = load_protocols();
+    // Mais nova primeiro: é a versão contra a qual o código do bot é escrito
+    protocols.sort_by(|a, b| b.version.cmp(&a.version));
+
+    for protocol in &protocols {
+        validate(protocol);
+    }
+    validate_registry(&protocols);
+
+    let current = &protocols[0];
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR não definido");
+    fs::write(Path::new(&out_dir).join("packet_ids.rs"), generate_ids(current))
+        .expect("Falha ao escrever packet_ids.rs");
+    fs::write(Path::new(&out_dir).join("packet_structs.rs"), generate_structs(current))
+        .expect("Falha ao escrever packet_structs.rs");
+    fs::write(Path::new(&out_dir).join("versions.rs"), generate_versions(&protocols))
+        .expect("Falha ao escrever versions.rs");
+}
+
+fn load_protocols() -> Vec<Protocol> {
+    let entries = fs::read_dir(PROTOCOL_DIR)
+        .unwrap_or_else(|e| panic!("Falha ao ler {}: {}", PROTOCOL_DIR, e));
+
+    let mut protocols = Vec::new();
+    for entry in entries {
+        let path = entry.expect("Falha ao listar descrições do protocolo").path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Falha ao ler {}: {}", path.display(), e));
+        let protocol: Protocol = serde_json::from_str(&source)
+            .unwrap_or_else(|e| panic!("Descrição do protocolo inválida em {}: {}", path.display(), e));
+        protocols.push(protocol);
+    }
+
+    if protocols.is_empty() {
+        panic!("Nenhuma descrição de protocolo em {}", PROTOCOL_DIR);
+    }
+    protocols
+}
+
+fn validate(protocol: &Protocol) {
+    if protocol.hash.len() != 64 || !protocol.hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        panic!("Protocol hash deve ter 64 caracteres hexadecimais");
+    }
+
+    for (i, packet) in protocol.packets.iter().enumerate() {
+        if let Some(other) = protocol.packets[..i].iter().find(|p| p.id == packet.id || p.name == packet.name) {
+            panic!("Pacote {} (ID: {}) duplicado com {} (ID: {})", packet.name, packet.id, other.name, other.id);
+        }
+        if packet.manual && packet.fields.is_some() {
+            panic!("Pacote {} é manual e não pode descrever campos", packet.name);
+        }
+    }
+}
+
+fn validate_registry(protocols: &[Protocol]) {
+    for (i, protocol) in protocols.iter().enumerate() {
+        if let Some(other) = protocols[..i].iter().find(|p| p.version == protocol.version || p.hash == protocol.hash) {
+            panic!("Versão {} duplicada com {} (mesma versão ou hash)", protocol.version, other.version);
+        }
+    }
+}
+
+// ============================================================================
+// Packet IDs (constants.rs)
+// ============================================================================
+
+fn generate_ids(protocol: &Protocol) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "/// Versão do protocolo contra a qual o bot é compilado").unwrap();
+    writeln!(out, "pub const PROTOCOL_VERSION: &str = {:?};", protocol.version).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "/// Protocol hash atual do Hytale (versão {})", protocol.version).unwrap();
+    writeln!(out, "pub const PROTOCOL_HASH: &[u8; 64] = b{:?};", protocol.hash).unwrap();
+    writeln!(out).unwrap();
+
+    for packet in &protocol.packets {
+        writeln!(out, "pub const PACKET_{}: u32 = {};", screaming_snake(&packet.name), packet.id).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "pub fn get_packet_name(id: u32) -> &'static str {{").unwrap();
+    writeln!(out, "    match id {{").unwrap();
+    for packet in &protocol.packets {
+        writeln!(out, "        {} => {:?},", packet.id, packet.name).unwrap();
+    }
+    writeln!(out, "        _ => \"Unknown\",").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+// ============================================================================
+// Structs de pacotes (packets.rs)
+// ============================================================================
+
+fn generate_structs(protocol: &Protocol) -> String {
+    let mut out = String::new();
+
+    for packet in &protocol.packets {
+        let Some(fields) = &packet.fields else { continue };
+
+        if let Some(doc) = &packet.doc {
+            writeln!(out, "/// {}", doc).unwrap();
+        }
+        writeln!(out, "#[derive(Debug, Clone, PartialEq, Eq, HytalePacket)]").unwrap();
+        writeln!(out, "#[packet(id = PACKET_{})]", screaming_snake(&packet.name)).unwrap();
+
+        if fields.is_empty() {
+            writeln!(out, "pub struct {}Packet;", packet.name).unwrap();
+            writeln!(out).unwrap();
+            continue;
+        }
+
+        writeln!(out, "pub struct {}Packet {{", packet.name).unwrap();
+        for field in fields {
+            if field.nullable {
+                writeln!(out, "    #[nullable]").unwrap();
+            }
+            match field.fixed {
+                Some(Fixed::Ascii(size)) => writeln!(out, "    #[fixed({})]", size).unwrap(),
+                Some(Fixed::Codec(true)) => writeln!(out, "    #[fixed]").unwrap(),
+                Some(Fixed::Codec(false)) | None => {}
+            }
+            writeln!(out, "    pub {}: {},", field.name, rust_type(field)).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+    }
+
+    out
+}
+
+// ============================================================================
+// Registro de versões (versions.rs)
+// ============================================================================
+
+fn generate_versions(protocols: &[Protocol]) -> String {
+    let current = &protocols[0];
+    let mut out = String::new();
+
+    for protocol in protocols {
+        writeln!(out, "/// Protocolo da versão {}", protocol.version).unwrap();
+        writeln!(out, "pub static {}: ProtocolVersion = ProtocolVersion {{", version_ident(&protocol.version)).unwrap();
+        writeln!(out, "    version: {:?},", protocol.version).unwrap();
+        writeln!(out, "    hash: {:?},", protocol.hash).unwrap();
+        writeln!(out, "    packets: &[").unwrap();
+        for packet in &protocol.packets {
+            writeln!(
+                out,
+                "        PacketEntry {{ id: {}, name: {:?}, compatible: {}, compressed: {} }},",
+                packet.id, packet.name, is_compatible(packet, current), packet.compressed
+            ).unwrap();
+        }
+        writeln!(out, "    ],").unwrap();
+        writeln!(out, "}};").unwrap();
+        writeln!(out).unwrap();
+    }
+
+    writeln!(out, "/// Versões conhecidas, da mais nova para a mais antiga").unwrap();
+    let idents: Vec<String> = protocols.iter().map(|p| format!("&{}", version_ident(&p.version))).collect();
+    writeln!(out, "pub static VERSIONS: &[&ProtocolVersion] = &[{}];", idents.join(", ")).unwrap();
+
+    out
+}
+
+/// Um pacote só pode ser codificado pelas structs geradas se o layout for o
+/// mesmo da versão atual
+fn is_compatible(packet: &PacketDef, current: &Protocol) -> bool {
+    let Some(reference) = current.packets.iter().find(|p| p.name == packet.name) else {
+        return false;
+    };
+
+    match (&packet.fields, &reference.fields) {
+        (Some(fields), Some(reference_fields)) => fields == reference_fields,
+        _ => packet.manual == reference.manual,
+    }
+}
+
+/// "2026.01.13" -> "V2026_01_13"
+fn version_ident(version: &str) -> String {
+    let ident: String = version.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    format!("V{}", ident)
+}
+
+fn rust_type(field: &FieldDef) -> String {
+    let mut ty = match field.ty.as_str() {
+        "Bytes" => "Vec<u8>".to_string(),
+        other => other.to_string(),
+    };
+    if field.array {
+        ty = format!("Vec<{}>", ty);
+    }
+    if field.nullable {
+        ty = format!("Option<{}>", ty);
+    }
+    ty
+}
+
+/// "ServerAuthToken" -> "SERVER_AUTH_TOKEN"
+fn screaming_snake(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
@@ -0,0 +1,213 @@
+//! Derive macro for Hytale packet structs
+//!
+//! ```ignore
+//! #[derive(HytalePacket)]
+//! #[packet(id = PACKET_AUTH_GRANT)]
+//! pub struct AuthGrantPacket {
+//!     #[nullable]
+//!     pub authorization_grant: Option<String>,
+//!     #[nullable]
+//!     pub server_identity_token: Option<String>,
+//! }
+//! ```
+//!
+//! Os campos seguem a ordem de declaração: os fixos (primitivos, `Uuid`,
+//! `#[fixed]` e `#[fixed(N)]`) vêm antes dos variáveis, e cada campo
+//! `#[nullable]` recebe o próximo bit do nullBits.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, GenericArgument, LitInt, PathArguments, Type};
+
+/// Tipos que sempre vão no fixed block
+const FIXED_PRIMITIVES: &[&str] = &["u8", "u16", "i16", "i32", "u32", "i64", "f32", "f64", "bool", "Uuid"];
+
+#[proc_macro_derive(HytalePacket, attributes(packet, nullable, fixed))]
+pub fn derive_hytale_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// ============================================================================
+// Descrição dos campos
+// ============================================================================
+
+enum Kind {
+    /// String ASCII de tamanho fixo
+    FixedAscii(LitInt),
+    /// FixedCodec no fixed block
+    Fixed,
+    /// VarCodec no variable block
+    Var,
+}
+
+struct FieldSpec {
+    ident: syn::Ident,
+    kind: Kind,
+    nullable: bool,
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_fixed_primitive(ty: &Type) -> bool {
+    let Type::Path(path) = ty else { return false };
+    path.path.segments.last()
+        .is_some_and(|segment| FIXED_PRIMITIVES.iter().any(|name| segment.ident == name))
+}
+
+fn field_spec(field: &syn::Field) -> syn::Result<FieldSpec> {
+    let ident = field.ident.clone()
+        .ok_or_else(|| syn::Error::new(field.span(), "HytalePacket requer campos nomeados"))?;
+
+    let mut nullable = false;
+    let mut fixed: Option<Option<LitInt>> = None;
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("nullable") {
+            attr.meta.require_path_only()?;
+            nullable = true;
+        } else if attr.path().is_ident("fixed") {
+            fixed = Some(match &attr.meta {
+                syn::Meta::Path(_) => None,
+                _ => Some(attr.parse_args::<LitInt>()?),
+            });
+        }
+    }
+
+    let inner = option_inner(&field.ty);
+    match (nullable, inner.is_some()) {
+        (true, false) => return Err(syn::Error::new(field.ty.span(), "#[nullable] requer um campo Option<T>")),
+        (false, true) => return Err(syn::Error::new(field.ty.span(), "campos Option<T> precisam de #[nullable]")),
+        _ => {}
+    }
+    let ty = inner.unwrap_or(&field.ty);
+
+    let kind = match fixed {
+        Some(Some(size)) => {
+            if nullable {
+                return Err(syn::Error::new(size.span(), "#[fixed(N)] não suporta campos anuláveis"));
+            }
+            Kind::FixedAscii(size)
+        }
+        Some(None) => Kind::Fixed,
+        None if is_fixed_primitive(ty) => Kind::Fixed,
+        None => Kind::Var,
+    };
+
+    Ok(FieldSpec { ident, kind, nullable })
+}
+
+// ============================================================================
+// Geração de código
+// ============================================================================
+
+fn packet_id(input: &DeriveInput) -> syn::Result<Expr> {
+    let mut id = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("packet")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                id = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("atributo desconhecido, esperado `id = ...`"))
+            }
+        })?;
+    }
+    id.ok_or_else(|| syn::Error::new(input.ident.span(), "faltando #[packet(id = ...)]"))
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let id = packet_id(input)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(name.span(), "HytalePacket só pode ser derivado para structs"));
+    };
+
+    let specs = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().map(field_spec).collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => Vec::new(),
+        Fields::Unnamed(fields) => {
+            return Err(syn::Error::new(fields.span(), "HytalePacket requer campos nomeados"));
+        }
+    };
+
+    // O fixed block precisa vir inteiro antes do variable block
+    let mut seen_var = false;
+    for spec in &specs {
+        match spec.kind {
+            Kind::Var => seen_var = true,
+            _ if seen_var => {
+                return Err(syn::Error::new(spec.ident.span(), "campos fixos devem vir antes dos campos variáveis"));
+            }
+            _ => {}
+        }
+    }
+
+    let nullable_fields = specs.iter().filter(|spec| spec.nullable).count();
+    let var_fields = specs.iter().filter(|spec| matches!(spec.kind, Kind::Var)).count();
+
+    let writes = specs.iter().map(|spec| {
+        let ident = &spec.ident;
+        match (&spec.kind, spec.nullable) {
+            (Kind::FixedAscii(size), _) => quote! { .fixed_ascii(&self.#ident, #size) },
+            (Kind::Fixed, false) => quote! { .fixed(&self.#ident) },
+            (Kind::Fixed, true) => quote! { .opt_fixed(self.#ident.as_ref()) },
+            (Kind::Var, false) => quote! { .var(&self.#ident) },
+            (Kind::Var, true) => quote! { .opt_var(self.#ident.as_ref()) },
+        }
+    });
+
+    let reads = specs.iter().map(|spec| {
+        let ident = &spec.ident;
+        let read = match (&spec.kind, spec.nullable) {
+            (Kind::FixedAscii(size), _) => quote! { reader.fixed_ascii(#size)? },
+            (Kind::Fixed, false) => quote! { reader.fixed()? },
+            (Kind::Fixed, true) => quote! { reader.opt_fixed()? },
+            (Kind::Var, false) => quote! { reader.var()? },
+            (Kind::Var, true) => quote! { reader.opt_var()? },
+        };
+        quote! { let #ident = #read; }
+    });
+
+    let idents = specs.iter().map(|spec| &spec.ident);
+    let construct = match &data.fields {
+        Fields::Unit => quote! { Self },
+        _ => quote! { Self { #(#idents),* } },
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::hytale_bot::protocol::Packet for #name #ty_generics #where_clause {
+            const ID: u32 = #id;
+
+            fn encode(&self, buf: &mut ::std::vec::Vec<u8>) {
+                ::hytale_bot::protocol::LayoutWriter::new()
+                    #(#writes)*
+                    .finish(buf);
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn decode(data: &[u8]) -> ::std::option::Option<Self> {
+                let mut reader = ::hytale_bot::protocol::LayoutReader::new(data, #nullable_fields, #var_fields);
+                #(#reads)*
+                ::std::option::Option::Some(#construct)
+            }
+        }
+    })
+}
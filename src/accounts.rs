@@ -0,0 +1,133 @@
+//! Per-account storage directory
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Diretório com uma pasta por conta (tokens, certificado do cliente)
+#[derive(Debug, Clone)]
+pub struct AccountStore {
+    root: PathBuf,
+}
+
+impl AccountStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `<config do usuário>/hytale-bot/accounts`, ou `./.hytale-bot/accounts` se não houver
+    pub fn default_root() -> PathBuf {
+        config_root().join("accounts")
+    }
+
+    /// Pasta `accounts` dentro de outra pasta de configuração
+    pub fn in_config_dir(config_dir: &Path) -> Self {
+        Self::new(config_dir.join("accounts"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Pasta da conta, criada com permissão só para o dono
+    pub fn account_dir(&self, account: &str) -> io::Result<PathBuf> {
+        validate_account_name(account)?;
+
+        let dir = self.root.join(account);
+        create_private_dir(&dir)?;
+        Ok(dir)
+    }
+
+    /// Contas com alguma coisa salva, em ordem alfabética
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut accounts = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir()
+                && let Some(name) = entry.file_name().to_str()
+            {
+                accounts.push(name.to_string());
+            }
+        }
+        accounts.sort();
+        Ok(accounts)
+    }
+}
+
+impl Default for AccountStore {
+    fn default() -> Self {
+        Self::new(Self::default_root())
+    }
+}
+
+/// `<config do usuário>/hytale-bot`, ou `./.hytale-bot` se não houver
+pub fn config_root() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join("hytale-bot"))
+        .unwrap_or_else(|| PathBuf::from(".hytale-bot"))
+}
+
+/// O nome vira nome de pasta, então não pode escapar do diretório das contas
+fn validate_account_name(account: &str) -> io::Result<()> {
+    let valid = !account.is_empty()
+        && !account.starts_with('.')
+        && account.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Nome de conta inválido: {:?} (use letras, números, '_', '-' e '.')", account),
+        ));
+    }
+    Ok(())
+}
+
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+/// Escreve o arquivo legível só pelo dono, trocando o antigo de uma vez
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = stage_private(path, contents)?;
+    fs::rename(&tmp, path)
+}
+
+/// Grava o temporário de `path` sem tocar no arquivo; renomear o caminho retornado conclui a troca
+///
+/// Serve para arquivos que andam juntos: todos ficam no disco antes de qualquer um ser trocado.
+pub fn stage_private(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    // `client.crt` e `client.key` não podem dividir o mesmo `client.tmp`
+    let mut tmp_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    // A permissão só é aplicada na criação, então um temporário antigo não pode ser reaproveitado
+    let _ = fs::remove_file(&tmp);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    Ok(tmp)
+}
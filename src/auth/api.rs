@@ -1,87 +1,83 @@
 //! Hytale API calls for server authentication
 
-use reqwest::Client;
-
-type BoxError = Box<dyn std::error::Error + Send + Sync>;
+use tracing::info;
+use crate::utils::{extract_jwt_cert_thumbprint, fingerprints_match};
+use super::client::AuthClient;
+use super::error::{http_error, read_json, AuthError};
 
 /// Troca um authorization grant por um access token
+///
+/// O token precisa estar preso ao certificado da conexão (`cnf.x5t#S256`), senão o
+/// servidor recusaria o AuthToken; o erro aparece aqui, antes de enviá-lo.
 pub async fn exchange_grant_for_access_token(
+    client: &AuthClient,
     authorization_grant: &str,
     session_token: &str,
     x509_fingerprint: &str,
-) -> Result<String, BoxError> {
-    let client = Client::new();
-    let url = "https://sessions.hytale.com/server-join/auth-token";
+) -> Result<String, AuthError> {
+    let url = client.endpoints().sessions_url("/server-join/auth-token");
 
     let body = serde_json::json!({
         "authorizationGrant": authorization_grant,
-        "x509Fingerprint": x509_fingerprint.trim_end_matches("=")
+        "x509Fingerprint": x509_fingerprint
     });
 
     let response = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", session_token))
         .header("Content-Type", "application/json")
-        .header("User-Agent", "Hytale/1.0")
         .json(&body)
         .send()
-        .await?;
+        .await
+        .map_err(http_error(&url))?;
 
-    if response.status().is_success() {
-        let resp_text = response.text().await?;
-        let json: serde_json::Value = serde_json::from_str(&resp_text)?;
+    let json: serde_json::Value = read_json(&url, response).await?;
+    let access_token = json.get("accessToken")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| AuthError::MissingField { endpoint: url.clone(), field: "accessToken" })?;
 
-        if let Some(access_token) = json.get("accessToken").and_then(|v| v.as_str()) {
-            println!("✅ AccessToken obtido da API!");
-            Ok(access_token.to_string())
-        } else {
-            Err(format!("Resposta sem accessToken: {}", resp_text).into())
-        }
-    } else {
-        let status = response.status();
-        let err_text = response.text().await?;
-        Err(format!("Erro ao trocar grant por token: {} - {}", status, err_text).into())
+    let bound_to = extract_jwt_cert_thumbprint(access_token);
+    if !bound_to.as_deref().is_some_and(|bound| fingerprints_match(bound, x509_fingerprint)) {
+        return Err(AuthError::CertificateBinding {
+            expected: x509_fingerprint.to_string(),
+            actual: bound_to,
+        });
     }
+
+    info!("✅ AccessToken obtido da API (certificado confere)!");
+    Ok(access_token.to_string())
 }
 
 /// Gera um authorization grant para o servidor
 pub async fn request_server_auth_grant(
+    client: &AuthClient,
     identity_token: &str,
     server_audience: &str,
     session_token: &str,
-) -> Result<String, BoxError> {
-    let client = Client::new();
-    let url = "https://sessions.hytale.com/server-join/auth-grant";
+) -> Result<String, AuthError> {
+    let url = client.endpoints().sessions_url("/server-join/auth-grant");
 
     let body = serde_json::json!({
         "identityToken": identity_token,
         "aud": server_audience
     });
 
-    println!("🔄 Gerando grant para servidor (aud: {})...", server_audience);
+    info!("🔄 Gerando grant para servidor (aud: {})...", server_audience);
 
     let response = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", session_token))
         .header("Content-Type", "application/json")
-        .header("User-Agent", "Hytale/1.0")
         .json(&body)
         .send()
-        .await?;
+        .await
+        .map_err(http_error(&url))?;
 
-    if response.status().is_success() {
-        let resp_text = response.text().await?;
-        let json: serde_json::Value = serde_json::from_str(&resp_text)?;
+    let json: serde_json::Value = read_json(&url, response).await?;
+    let grant = json.get("authorizationGrant")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| AuthError::MissingField { endpoint: url.clone(), field: "authorizationGrant" })?;
 
-        if let Some(grant) = json.get("authorizationGrant").and_then(|v| v.as_str()) {
-            println!("✅ Grant para servidor obtido!");
-            Ok(grant.to_string())
-        } else {
-            Err(format!("Resposta sem authorizationGrant: {}", resp_text).into())
-        }
-    } else {
-        let status = response.status();
-        let err_text = response.text().await?;
-        Err(format!("Erro ao gerar grant para servidor: {} - {}", status, err_text).into())
-    }
+    info!("✅ Grant para servidor obtido!");
+    Ok(grant.to_string())
 }
@@ -0,0 +1,168 @@
+//! On-disk OAuth token cache with refresh-token support
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::accounts::{write_private, AccountStore};
+use crate::utils::unix_now;
+use super::client::AuthClient;
+use super::error::AuthError;
+use super::oauth::{get_access_token, refresh_access_token, LoginMode, TokenResponse};
+
+const TOKENS_FILE: &str = "tokens.json";
+
+/// Margem para não usar um token que vai expirar no meio da conexão
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+// ============================================================================
+// Tipos
+// ============================================================================
+
+/// Tokens salvos em disco, com a expiração em tempo absoluto
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Unix timestamp (segundos) em que o access token expira
+    pub expires_at: u64,
+    pub scope: String,
+    pub token_type: String,
+}
+
+impl CachedTokens {
+    fn from_response(response: &TokenResponse) -> Self {
+        Self {
+            access_token: response.access_token.clone(),
+            refresh_token: response.refresh_token.clone(),
+            expires_at: unix_now() + response.expires_in,
+            scope: response.scope.clone(),
+            token_type: response.token_type.clone(),
+        }
+    }
+
+    /// Indica se o access token ainda vale por pelo menos `EXPIRY_MARGIN_SECS`
+    pub fn is_valid(&self) -> bool {
+        self.expires_at > unix_now() + EXPIRY_MARGIN_SECS
+    }
+
+    fn to_response(&self) -> TokenResponse {
+        TokenResponse {
+            access_token: self.access_token.clone(),
+            refresh_token: self.refresh_token.clone(),
+            expires_in: self.expires_at.saturating_sub(unix_now()),
+            scope: self.scope.clone(),
+            token_type: self.token_type.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// Cache
+// ============================================================================
+
+/// Cache de tokens por conta dentro do `AccountStore`
+#[derive(Debug, Clone, Default)]
+pub struct TokenCache {
+    store: AccountStore,
+}
+
+impl TokenCache {
+    pub fn new(store: AccountStore) -> Self {
+        Self { store }
+    }
+
+    fn path(&self, account: &str) -> Result<PathBuf, AuthError> {
+        let dir = self.store.account_dir(account)
+            .map_err(|source| AuthError::Cache { path: self.store.root().join(account), source })?;
+        Ok(dir.join(TOKENS_FILE))
+    }
+
+    /// Lê os tokens da conta, None se não houver cache (ou se ele estiver corrompido)
+    pub fn load(&self, account: &str) -> Result<Option<CachedTokens>, AuthError> {
+        let path = self.path(account)?;
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(AuthError::Cache { path, source }),
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(tokens) => Ok(Some(tokens)),
+            Err(e) => {
+                warn!("⚠️ Cache de tokens corrompido em {} ({}), ignorando", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn store(&self, account: &str, tokens: &CachedTokens) -> Result<(), AuthError> {
+        let path = self.path(account)?;
+        let contents = serde_json::to_vec_pretty(tokens).map_err(io::Error::other);
+
+        contents
+            .and_then(|contents| write_private(&path, &contents))
+            .map_err(|source| AuthError::Cache { path, source })
+    }
+
+    pub fn remove(&self, account: &str) -> Result<(), AuthError> {
+        let path = self.path(account)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(source) => Err(AuthError::Cache { path, source }),
+        }
+    }
+}
+
+// ============================================================================
+// Login
+// ============================================================================
+
+/// Retorna um access token válido para a conta
+///
+/// Usa o cache enquanto o token for válido, depois tenta o `refresh_token` e só
+/// faz o login interativo (navegador ou headless, conforme `mode`) se não houver
+/// como renovar.
+pub async fn login(client: &AuthClient, cache: &TokenCache, account: &str, mode: &LoginMode) -> Result<TokenResponse, AuthError> {
+    let cached = cache.load(account)?;
+
+    if let Some(tokens) = &cached
+        && tokens.is_valid()
+    {
+        info!("🔑 Usando token em cache da conta {}", account);
+        return Ok(tokens.to_response());
+    }
+
+    if let Some(refresh_token) = cached.as_ref().and_then(|t| t.refresh_token.as_deref()) {
+        info!("🔄 Token expirado, renovando com refresh_token...");
+        match refresh_access_token(client, refresh_token).await {
+            Ok(mut response) => {
+                // O servidor pode não rotacionar o refresh token
+                if response.refresh_token.is_none() {
+                    response.refresh_token = Some(refresh_token.to_string());
+                }
+                cache.store(account, &CachedTokens::from_response(&response))?;
+                return Ok(response);
+            }
+            Err(e) => warn!("⚠️ Falha ao renovar token ({}), fazendo login...", e),
+        }
+    }
+
+    let response = get_access_token(client, mode).await?;
+    cache.store(account, &CachedTokens::from_response(&response))?;
+    Ok(response)
+}
+
+/// Cadastra a conta a partir de um refresh token obtido em outra máquina
+pub async fn import_refresh_token(client: &AuthClient, cache: &TokenCache, account: &str, refresh_token: &str) -> Result<TokenResponse, AuthError> {
+    let mut response = refresh_access_token(client, refresh_token.trim()).await?;
+    if response.refresh_token.is_none() {
+        response.refresh_token = Some(refresh_token.trim().to_string());
+    }
+
+    cache.store(account, &CachedTokens::from_response(&response))?;
+    Ok(response)
+}
@@ -0,0 +1,100 @@
+//! Shared HTTP client and endpoint configuration for the auth services
+
+use std::sync::Arc;
+use reqwest::header::USER_AGENT;
+use reqwest::{Client, Method, RequestBuilder};
+
+// ============================================================================
+// Endpoints
+// ============================================================================
+
+/// Onde ficam o OAuth e o session service, para apontar o fluxo para um mock ou staging
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthEndpoints {
+    /// Base do OAuth, sem barra no final (ex: `https://oauth.accounts.hytale.com`)
+    pub oauth_base_url: String,
+    /// Base do session service, sem barra no final (ex: `https://sessions.hytale.com`)
+    pub sessions_base_url: String,
+    pub client_id: String,
+    /// Página para onde o OAuth manda o navegador depois do login
+    pub redirect_uri: String,
+    pub user_agent: String,
+}
+
+impl AuthEndpoints {
+    /// Serviços oficiais do Hytale
+    pub fn production() -> Self {
+        Self {
+            oauth_base_url: "https://oauth.accounts.hytale.com".to_string(),
+            sessions_base_url: "https://sessions.hytale.com".to_string(),
+            client_id: "hytale-launcher".to_string(),
+            redirect_uri: "https://accounts.hytale.com/consent/client".to_string(),
+            user_agent: "Hytale/1.0".to_string(),
+        }
+    }
+
+    /// Mesmo client ID e redirect, com os dois serviços na mesma base (ex: mock local)
+    pub fn with_base_url(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        Self {
+            oauth_base_url: base_url.to_string(),
+            sessions_base_url: base_url.to_string(),
+            ..Self::production()
+        }
+    }
+
+    pub fn oauth_url(&self, path: &str) -> String {
+        format!("{}{}", self.oauth_base_url.trim_end_matches('/'), path)
+    }
+
+    pub fn sessions_url(&self, path: &str) -> String {
+        format!("{}{}", self.sessions_base_url.trim_end_matches('/'), path)
+    }
+}
+
+impl Default for AuthEndpoints {
+    fn default() -> Self {
+        Self::production()
+    }
+}
+
+// ============================================================================
+// Cliente
+// ============================================================================
+
+/// Cliente HTTP compartilhado por login, sessão e autenticação com o servidor
+///
+/// Clonar é barato: o pool de conexões do `reqwest` e os endpoints são compartilhados.
+#[derive(Debug, Clone, Default)]
+pub struct AuthClient {
+    http: Client,
+    endpoints: Arc<AuthEndpoints>,
+}
+
+impl AuthClient {
+    pub fn new(endpoints: AuthEndpoints) -> Self {
+        Self {
+            http: Client::new(),
+            endpoints: Arc::new(endpoints),
+        }
+    }
+
+    pub fn endpoints(&self) -> &AuthEndpoints {
+        &self.endpoints
+    }
+
+    /// Requisição já com o User-Agent configurado
+    pub(crate) fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.http
+            .request(method, url)
+            .header(USER_AGENT, &self.endpoints.user_agent)
+    }
+
+    pub(crate) fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub(crate) fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+}
@@ -0,0 +1,103 @@
+//! Authentication errors
+
+use std::path::PathBuf;
+use reqwest::{Response, StatusCode};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+/// Falhas do login OAuth e das chamadas às APIs de sessão do Hytale
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("Erro HTTP em {endpoint}: {source}")]
+    Http {
+        endpoint: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("{endpoint} respondeu {status}: {body}")]
+    HttpStatus {
+        endpoint: String,
+        status: StatusCode,
+        body: String,
+    },
+
+    #[error("Resposta inválida de {endpoint}: {source}")]
+    InvalidResponse {
+        endpoint: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Resposta de {endpoint} sem {field}")]
+    MissingField {
+        endpoint: String,
+        field: &'static str,
+    },
+
+    #[error("Access token preso a outro certificado (esperado {expected}, token tem {})", actual.as_deref().unwrap_or("nenhum"))]
+    CertificateBinding {
+        expected: String,
+        actual: Option<String>,
+    },
+
+    #[error("Falha ao receber o código de autorização")]
+    CallbackClosed,
+
+    #[error("Nenhum código de autorização informado")]
+    MissingCode,
+
+    #[error("Erro lendo o código de autorização: {0}")]
+    CodeInput(#[source] std::io::Error),
+
+    #[error("URL de login inválida: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[error("Erro no cache de tokens em {}: {source}", path.display())]
+    Cache {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl AuthError {
+    /// Indica se vale tentar de novo (rede ou erro do lado do servidor)
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AuthError::Http { .. } => true,
+            AuthError::HttpStatus { status, .. } => status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+}
+
+/// Converte a resposta em JSON, ou em `HttpStatus` se não for sucesso
+pub(crate) async fn read_json<T: DeserializeOwned>(endpoint: &str, response: Response) -> Result<T, AuthError> {
+    let status = response.status();
+    let body = response.text().await
+        .map_err(|source| AuthError::Http { endpoint: endpoint.to_string(), source })?;
+
+    if !status.is_success() {
+        return Err(AuthError::HttpStatus { endpoint: endpoint.to_string(), status, body });
+    }
+
+    serde_json::from_str(&body)
+        .map_err(|source| AuthError::InvalidResponse { endpoint: endpoint.to_string(), source })
+}
+
+/// Confere só o status, para respostas sem corpo útil
+pub(crate) async fn expect_success(endpoint: &str, response: Response) -> Result<(), AuthError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(AuthError::HttpStatus { endpoint: endpoint.to_string(), status, body })
+}
+
+/// Erro de envio da requisição
+pub(crate) fn http_error(endpoint: &str) -> impl FnOnce(reqwest::Error) -> AuthError + '_ {
+    move |source| AuthError::Http { endpoint: endpoint.to_string(), source }
+}
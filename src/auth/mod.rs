@@ -1,7 +1,13 @@
+pub mod client;
 pub mod oauth;
 pub mod session;
 pub mod api;
+pub mod error;
+pub mod cache;
 
+pub use client::{AuthClient, AuthEndpoints};
 pub use oauth::*;
 pub use session::*;
 pub use api::*;
+pub use error::AuthError;
+pub use cache::*;
@@ -2,23 +2,28 @@
 
 use base64::{engine::general_purpose, Engine as _};
 use rand::{distributions::Alphanumeric, Rng};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::sync::oneshot;
 use warp::Filter;
+use super::client::{AuthClient, AuthEndpoints};
+use super::error::{http_error, read_json, AuthError};
 
 // ============================================================================
 // Constantes OAuth
 // ============================================================================
 
-const CLIENT_ID: &str = "hytale-launcher";
-const AUTH_URL: &str = "https://oauth.accounts.hytale.com/oauth2/auth";
-const TOKEN_URL: &str = "https://oauth.accounts.hytale.com/oauth2/token";
-const REDIRECT_URI: &str = "https://accounts.hytale.com/consent/client";
+const AUTH_PATH: &str = "/oauth2/auth";
+const TOKEN_PATH: &str = "/oauth2/token";
 const LOCAL_PORT: u16 = 43803;
 
+/// Intervalo entre leituras do arquivo de código no login headless
+const CODE_FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 // ============================================================================
 // Tipos
 // ============================================================================
@@ -66,17 +71,66 @@ fn generate_challenge(verifier: &str) -> String {
 // OAuth Flow
 // ============================================================================
 
+/// Como o código de autorização chega até o bot
+#[derive(Debug, Clone, Default)]
+pub enum LoginMode {
+    /// Abre o navegador e recebe o código no callback local
+    #[default]
+    Browser,
+    /// Só imprime a URL, o código (ou a URL de redirect inteira) é colado no stdin
+    Stdin,
+    /// Só imprime a URL, o código (ou a URL de redirect inteira) é lido de um arquivo
+    File(PathBuf),
+}
+
 /// Inicia o fluxo OAuth e retorna os tokens de acesso
-pub async fn get_access_token() -> Result<TokenResponse, Box<dyn std::error::Error>> {
+pub async fn get_access_token(client: &AuthClient, mode: &LoginMode) -> Result<TokenResponse, AuthError> {
     let code_verifier = generate_verifier();
     let code_challenge = generate_challenge(&code_verifier);
+    let url = build_login_url(client.endpoints(), &code_challenge)?;
+
+    let auth_code = match mode {
+        LoginMode::Browser => receive_code_from_browser(&url).await?,
+        LoginMode::Stdin => {
+            print_headless_instructions(&url);
+            println!("Cole o código ou a URL aqui:");
+            read_code_from_stdin().await?
+        }
+        LoginMode::File(path) => {
+            print_headless_instructions(&url);
+            println!("Aguardando o código ou a URL em {}...", path.display());
+            read_code_from_file(path).await?
+        }
+    };
 
+    exchange_code(client, &auth_code, &code_verifier).await
+}
+
+fn build_login_url(endpoints: &AuthEndpoints, code_challenge: &str) -> Result<url::Url, AuthError> {
     let random_state: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
         .take(16)
         .map(char::from)
         .collect();
 
+    let state_json = serde_json::json!(StateData {
+        state: random_state,
+        port: LOCAL_PORT.to_string(),
+    }).to_string();
+    let state_encoded = general_purpose::URL_SAFE_NO_PAD.encode(state_json);
+
+    Ok(url::Url::parse_with_params(&endpoints.oauth_url(AUTH_PATH), &[
+        ("client_id", endpoints.client_id.as_str()),
+        ("response_type", "code"),
+        ("scope", "openid offline auth:launcher"),
+        ("redirect_uri", &endpoints.redirect_uri),
+        ("code_challenge_method", "S256"),
+        ("code_challenge", code_challenge),
+        ("state", &state_encoded),
+    ])?)
+}
+
+async fn receive_code_from_browser(url: &url::Url) -> Result<String, AuthError> {
     // Servidor local para receber callback
     let (code_tx, code_rx) = oneshot::channel::<String>();
     let code_tx = Arc::new(Mutex::new(Some(code_tx)));
@@ -93,23 +147,6 @@ pub async fn get_access_token() -> Result<TokenResponse, Box<dyn std::error::Err
 
     let server_handle = tokio::spawn(warp::serve(callback_route).run(([127, 0, 0, 1], LOCAL_PORT)));
 
-    // Construir URL de login
-    let state_json = serde_json::to_string(&StateData {
-        state: random_state,
-        port: LOCAL_PORT.to_string(),
-    })?;
-    let state_encoded = general_purpose::URL_SAFE_NO_PAD.encode(state_json);
-
-    let url = url::Url::parse_with_params(AUTH_URL, &[
-        ("client_id", CLIENT_ID),
-        ("response_type", "code"),
-        ("scope", "openid offline auth:launcher"),
-        ("redirect_uri", REDIRECT_URI),
-        ("code_challenge_method", "S256"),
-        ("code_challenge", &code_challenge),
-        ("state", &state_encoded),
-    ])?;
-
     println!("\n🌐 Abrindo navegador para login...");
     println!("Link (caso não abra): {}", url);
 
@@ -118,27 +155,105 @@ pub async fn get_access_token() -> Result<TokenResponse, Box<dyn std::error::Err
     }
 
     let auth_code = code_rx.await
-        .map_err(|_| "Falha ao receber o código de autorização.")?;
+        .map_err(|_| AuthError::CallbackClosed);
 
     server_handle.abort();
+    auth_code
+}
+
+// ============================================================================
+// Login Headless
+// ============================================================================
+
+fn print_headless_instructions(url: &url::Url) {
+    println!("\n🌐 Abra este link em qualquer navegador e faça login:");
+    println!("{}", url);
+    println!("\nNo fim o navegador vai para http://127.0.0.1:{}/authorization-callback?code=...", LOCAL_PORT);
+    println!("A página não carrega nesta máquina, tudo bem: copie a URL da barra de endereço.");
+}
+
+/// Aceita tanto o código puro quanto a URL de redirect com `?code=...`
+fn parse_code_input(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    match url::Url::parse(input) {
+        Ok(url) => url.query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, code)| code.into_owned()),
+        Err(_) => Some(input.to_string()),
+    }
+}
+
+async fn read_code_from_stdin() -> Result<String, AuthError> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    while let Some(line) = lines.next_line().await.map_err(AuthError::CodeInput)? {
+        match parse_code_input(&line) {
+            Some(code) => return Ok(code),
+            None if line.trim().is_empty() => continue,
+            None => println!("⚠️ URL sem parâmetro code, tente de novo:"),
+        }
+    }
+
+    Err(AuthError::MissingCode)
+}
+
+/// Espera o arquivo aparecer com o código, permitindo provisionar por scp/ssh
+async fn read_code_from_file(path: &Path) -> Result<String, AuthError> {
+    loop {
+        match tokio::fs::read_to_string(path).await {
+            Ok(contents) => {
+                if let Some(code) = parse_code_input(&contents) {
+                    // O código só vale uma vez, não deixar para a próxima execução
+                    let _ = tokio::fs::remove_file(path).await;
+                    return Ok(code);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AuthError::CodeInput(e)),
+        }
+
+        tokio::time::sleep(CODE_FILE_POLL_INTERVAL).await;
+    }
+}
+
+// ============================================================================
+// Tokens
+// ============================================================================
 
-    // Trocar code por token
-    let client = Client::new();
+/// Troca o código de autorização pelos tokens
+async fn exchange_code(client: &AuthClient, auth_code: &str, code_verifier: &str) -> Result<TokenResponse, AuthError> {
+    let endpoints = client.endpoints();
+    let url = endpoints.oauth_url(TOKEN_PATH);
     let params = [
-        ("client_id", CLIENT_ID),
+        ("client_id", endpoints.client_id.as_str()),
         ("grant_type", "authorization_code"),
-        ("redirect_uri", REDIRECT_URI),
-        ("code", &auth_code),
-        ("code_verifier", &code_verifier),
+        ("redirect_uri", &endpoints.redirect_uri),
+        ("code", auth_code),
+        ("code_verifier", code_verifier),
     ];
 
-    let response = client.post(TOKEN_URL).form(&params).send().await?;
+    let response = client.post(&url).form(&params).send().await
+        .map_err(http_error(&url))?;
 
-    if response.status().is_success() {
-        Ok(response.json().await?)
-    } else {
-        let status = response.status();
-        let body = response.text().await?;
-        Err(format!("Erro na troca do token: {} - {}", status, body).into())
-    }
+    read_json(&url, response).await
+}
+
+/// Renova o access token usando o refresh token de um login anterior
+pub async fn refresh_access_token(client: &AuthClient, refresh_token: &str) -> Result<TokenResponse, AuthError> {
+    let endpoints = client.endpoints();
+    let url = endpoints.oauth_url(TOKEN_PATH);
+    let params = [
+        ("client_id", endpoints.client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+    ];
+
+    let response = client.post(&url).form(&params).send().await
+        .map_err(http_error(&url))?;
+
+    read_json(&url, response).await
 }
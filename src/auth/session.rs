@@ -1,8 +1,26 @@
 //! Game session management
 
-use reqwest::Client;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use serde::Deserialize;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
+use tracing::{info, warn};
+use crate::utils::{extract_jwt_expiry, unix_now};
+use super::client::AuthClient;
+use super::error::{expect_success, http_error, read_json, AuthError};
+
+/// Quanto antes da expiração a sessão é renovada
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Espera mínima entre renovações, para sessões que duram menos que `REFRESH_MARGIN`
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Espera antes de tentar renovar de novo depois de uma falha temporária, dobrada a cada falha
+const REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Maior espera entre tentativas depois de falhas seguidas
+const MAX_REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Deserialize, Debug)]
 #[allow(non_snake_case)]
@@ -13,30 +31,198 @@ pub struct GameSessionResponse {
 
 /// Cria uma nova sessão de jogo com o servidor Hytale
 pub async fn create_game_session(
+    client: &AuthClient,
     access_token: &str,
     player_uuid: Uuid,
-) -> Result<GameSessionResponse, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let url = "https://sessions.hytale.com/game-session/new";
+) -> Result<GameSessionResponse, AuthError> {
+    let url = client.endpoints().sessions_url("/game-session/new");
 
     let body = serde_json::json!({
         "uuid": player_uuid.to_string()
     });
 
     let response = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", access_token))
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
-        .await?;
-
-    if response.status().is_success() {
-        let resp_text = response.text().await?;
-        let session: GameSessionResponse = serde_json::from_str(&resp_text)?;
-        Ok(session)
-    } else {
-        let err_text = response.text().await?;
-        Err(format!("Erro ao criar sessão de jogo: {}", err_text).into())
+        .await
+        .map_err(http_error(&url))?;
+
+    read_json(&url, response).await
+}
+
+/// Renova a sessão, retornando novos identity/session tokens
+pub async fn refresh_game_session(client: &AuthClient, session_token: &str) -> Result<GameSessionResponse, AuthError> {
+    let url = client.endpoints().sessions_url("/game-session/refresh");
+
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", session_token))
+        .header("Content-Type", "application/json")
+        .send()
+        .await
+        .map_err(http_error(&url))?;
+
+    read_json(&url, response).await
+}
+
+/// Encerra a sessão no serviço, invalidando os tokens
+pub async fn delete_game_session(client: &AuthClient, session_token: &str) -> Result<(), AuthError> {
+    let url = client.endpoints().sessions_url("/game-session");
+
+    let response = client
+        .delete(&url)
+        .header("Authorization", format!("Bearer {}", session_token))
+        .send()
+        .await
+        .map_err(http_error(&url))?;
+
+    expect_success(&url, response).await
+}
+
+// ============================================================================
+// GameSession
+// ============================================================================
+
+/// Tokens atuais da sessão, prontos para o handshake
+#[derive(Debug, Clone)]
+pub struct SessionTokens {
+    pub identity_token: String,
+    pub session_token: String,
+    /// Unix timestamp (segundos) em que o primeiro dos dois tokens expira
+    pub expires_at: Option<u64>,
+}
+
+impl From<GameSessionResponse> for SessionTokens {
+    fn from(response: GameSessionResponse) -> Self {
+        let expires_at = [&response.identityToken, &response.sessionToken]
+            .into_iter()
+            .filter_map(|token| extract_jwt_expiry(token))
+            .min();
+
+        Self {
+            identity_token: response.identityToken,
+            session_token: response.sessionToken,
+            expires_at,
+        }
+    }
+}
+
+/// Sessão de jogo renovada antes de expirar, compartilhável entre tasks
+///
+/// Os tokens duram cerca de uma hora; com a sessão renovada o bot pode reconectar
+/// horas depois sem um login novo.
+#[derive(Debug, Clone)]
+pub struct GameSession {
+    client: AuthClient,
+    tokens: Arc<RwLock<SessionTokens>>,
+}
+
+impl GameSession {
+    pub async fn create(client: &AuthClient, access_token: &str, player_uuid: Uuid) -> Result<Self, AuthError> {
+        let response = create_game_session(client, access_token, player_uuid).await?;
+        Ok(Self {
+            client: client.clone(),
+            tokens: Arc::new(RwLock::new(response.into())),
+        })
+    }
+
+    /// Cliente usado pela sessão, o mesmo serve para autenticar nos servidores
+    pub fn client(&self) -> &AuthClient {
+        &self.client
+    }
+
+    pub fn tokens(&self) -> SessionTokens {
+        self.tokens.read().unwrap().clone()
+    }
+
+    /// Indica se a sessão expira dentro de `REFRESH_MARGIN`
+    pub fn needs_refresh(&self) -> bool {
+        self.tokens.read().unwrap().expires_at
+            .is_some_and(|exp| exp <= unix_now() + REFRESH_MARGIN.as_secs())
+    }
+
+    pub async fn refresh(&self) -> Result<(), AuthError> {
+        let session_token = self.tokens().session_token;
+        let response = refresh_game_session(&self.client, &session_token).await?;
+
+        *self.tokens.write().unwrap() = response.into();
+        info!("🔄 Sessão de jogo renovada");
+        Ok(())
+    }
+
+    /// Renova só se estiver perto de expirar, para chamar antes de cada conexão
+    pub async fn ensure_fresh(&self) -> Result<(), AuthError> {
+        if self.needs_refresh() {
+            self.refresh().await?;
+        }
+        Ok(())
+    }
+
+    /// Task que renova a sessão pouco antes de cada expiração
+    pub fn spawn_refresher(&self) -> JoinHandle<()> {
+        let session = self.clone();
+
+        tokio::spawn(async move {
+            let mut retry_interval = REFRESH_RETRY_INTERVAL;
+
+            loop {
+                let Some(expires_at) = session.tokens().expires_at else {
+                    // Sem exp nos tokens não há como saber quando renovar
+                    return;
+                };
+                tokio::time::sleep(refresh_delay(expires_at)).await;
+
+                // Falhas temporárias tentam de novo com espera crescente, até renovar
+                while let Err(e) = session.refresh().await {
+                    warn!("⚠️ Falha ao renovar sessão de jogo: {}", e);
+                    if !e.is_transient() {
+                        return;
+                    }
+                    tokio::time::sleep(retry_interval).await;
+                    retry_interval = (retry_interval * 2).min(MAX_REFRESH_RETRY_INTERVAL);
+                }
+                retry_interval = REFRESH_RETRY_INTERVAL;
+            }
+        })
+    }
+
+    /// Encerra a sessão no serviço; os tokens deixam de valer
+    pub async fn delete(&self) -> Result<(), AuthError> {
+        delete_game_session(&self.client, &self.tokens().session_token).await
+    }
+}
+
+/// Quanto esperar até renovar tokens que expiram em `expires_at`
+///
+/// Tokens que duram menos que `REFRESH_MARGIN` renovariam sem parar: nesse caso
+/// espera metade do que resta, nunca menos que `MIN_REFRESH_INTERVAL`.
+fn refresh_delay(expires_at: u64) -> Duration {
+    let remaining = expires_at.saturating_sub(unix_now());
+    match remaining.checked_sub(REFRESH_MARGIN.as_secs()) {
+        Some(until_margin) if until_margin > 0 => Duration::from_secs(until_margin),
+        _ => Duration::from_secs(remaining / 2).max(MIN_REFRESH_INTERVAL),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_delay_waits_until_the_margin() {
+        let delay = refresh_delay(unix_now() + 3600);
+        let expected = 3600 - REFRESH_MARGIN.as_secs();
+        assert!((expected - 1..=expected).contains(&delay.as_secs()), "{:?}", delay);
+    }
+
+    #[test]
+    fn refresh_delay_is_clamped_for_short_sessions() {
+        let delay = refresh_delay(unix_now() + 240);
+        assert!((119..=120).contains(&delay.as_secs()), "{:?}", delay);
+        assert_eq!(refresh_delay(unix_now() + 10), MIN_REFRESH_INTERVAL);
+        assert_eq!(refresh_delay(unix_now().saturating_sub(60)), MIN_REFRESH_INTERVAL);
     }
 }
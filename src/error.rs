@@ -0,0 +1,67 @@
+//! Top-level connection errors
+
+use std::path::PathBuf;
+use thiserror::Error;
+use crate::auth::AuthError;
+use crate::protocol::{DisconnectKind, ProtocolError};
+
+/// Qualquer falha entre o login e a entrada no mundo
+#[derive(Debug, Error)]
+pub enum ConnectError {
+    #[error("Endereço do servidor inválido: {0} (use host[:porta])")]
+    InvalidAddress(String),
+
+    #[error("Falha ao resolver {address}: {source}")]
+    Resolve {
+        address: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Nenhum endereço encontrado para {0}")]
+    Unresolved(String),
+
+    #[error("Falha ao gerar certificado do cliente: {0}")]
+    CertificateGeneration(#[from] rcgen::Error),
+
+    #[error("Certificado do cliente em {}: {source}", path.display())]
+    Identity {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Configuração TLS inválida: {0}")]
+    Tls(#[from] rustls::Error),
+
+    #[error("Configuração TLS sem cipher suite utilizável pelo QUIC")]
+    NoInitialCipherSuite(#[from] quinn::crypto::rustls::NoInitialCipherSuite),
+
+    #[error("Opção de transporte QUIC fora do limite: {0}")]
+    InvalidTransport(&'static str),
+
+    #[error("Falha ao criar endpoint QUIC: {0}")]
+    Endpoint(#[from] std::io::Error),
+
+    #[error("Falha ao conectar ao servidor de jogo: {0}")]
+    Quic(#[from] quinn::ConnectError),
+
+    #[error("Falha ao conectar ao servidor de jogo: {0}")]
+    Connection(#[from] quinn::ConnectionError),
+
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+}
+
+impl ConnectError {
+    /// Categoria do Disconnect, se o servidor encerrou a conexão
+    pub fn disconnect_kind(&self) -> Option<DisconnectKind> {
+        match self {
+            ConnectError::Protocol(e) => e.disconnect_kind(),
+            _ => None,
+        }
+    }
+}
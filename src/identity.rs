@@ -0,0 +1,228 @@
+//! Client certificate identity used for mTLS
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::sign::CertifiedKey;
+use tracing::{info, warn};
+use crate::accounts::{stage_private, AccountStore};
+use crate::error::ConnectError;
+use crate::utils::x509_fingerprint;
+
+const CERT_FILE: &str = "client.crt";
+const KEY_FILE: &str = "client.key";
+
+/// Certificado self-signed e chave que o bot apresenta no mTLS
+///
+/// O servidor prende o access token ao fingerprint deste certificado, então manter o
+/// mesmo entre execuções deixa o fingerprint estável.
+#[derive(Debug)]
+pub struct ClientIdentity {
+    cert: CertificateDer<'static>,
+    key: PrivatePkcs8KeyDer<'static>,
+    cert_pem: String,
+    key_pem: String,
+    fingerprint: String,
+}
+
+impl ClientIdentity {
+    /// Novo certificado, válido só enquanto o processo roda se não for salvo
+    pub fn generate() -> Result<Self, ConnectError> {
+        let certified_key = rcgen::generate_simple_self_signed(vec!["hytale_client".to_string()])?;
+
+        let cert = certified_key.cert.der().clone();
+        let key = PrivatePkcs8KeyDer::from(certified_key.signing_key.serialize_der());
+
+        Ok(Self {
+            fingerprint: x509_fingerprint(&cert),
+            cert,
+            key,
+            cert_pem: certified_key.cert.pem(),
+            key_pem: certified_key.signing_key.serialize_pem(),
+        })
+    }
+
+    /// Certificado salvo na conta, None se a conta ainda não tem um
+    ///
+    /// Um `save` interrompido entre as duas trocas também dá None, para o par ser gerado de novo.
+    pub fn load(store: &AccountStore, account: &str) -> Result<Option<Self>, ConnectError> {
+        let (cert_path, key_path) = paths(store, account)?;
+
+        let (cert_pem, key_pem) = match (read_optional(&cert_path)?, read_optional(&key_path)?) {
+            (Some(cert_pem), Some(key_pem)) => (cert_pem, key_pem),
+            (None, None) => return Ok(None),
+            // A chave é trocada primeiro, então só ela sem o certificado é um primeiro `save` interrompido
+            (None, Some(_)) => {
+                warn!("⚠️ Chave sem certificado na conta {} (gravação interrompida), gerando outro par", account);
+                return Ok(None);
+            }
+            // Certificado sem chave não vem do `save`, e gerar outro por cima apagaria o que sobrou
+            (Some(_), None) => return Err(identity_error(&key_path, missing_half())),
+        };
+
+        let cert = CertificateDer::from_pem_slice(cert_pem.as_bytes())
+            .map_err(|e| identity_error(&cert_path, invalid_pem(e)))?;
+        let key = match PrivateKeyDer::from_pem_slice(key_pem.as_bytes()) {
+            Ok(PrivateKeyDer::Pkcs8(key)) => key,
+            Ok(_) => return Err(identity_error(&key_path, invalid_pem("chave precisa estar em PKCS#8"))),
+            Err(e) => return Err(identity_error(&key_path, invalid_pem(e))),
+        };
+
+        // Chave nova com o certificado antigo: `save` interrompido entre as duas trocas
+        let provider = rustls::crypto::ring::default_provider();
+        if CertifiedKey::from_der(vec![cert.clone()], PrivateKeyDer::Pkcs8(key.clone_key()), &provider).is_err() {
+            warn!("⚠️ Chave e certificado da conta {} não formam um par (gravação interrompida), gerando outro", account);
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            fingerprint: x509_fingerprint(&cert),
+            cert,
+            key,
+            cert_pem,
+            key_pem,
+        }))
+    }
+
+    /// Salva o par em PEM na pasta da conta, a chave legível só pelo dono
+    ///
+    /// Os dois temporários são gravados antes de qualquer troca, e a chave é sempre trocada
+    /// primeiro; o que uma queda entre as trocas deixar para trás o `load` reconhece.
+    pub fn save(&self, store: &AccountStore, account: &str) -> Result<(), ConnectError> {
+        let (cert_path, key_path) = paths(store, account)?;
+
+        let key_tmp = stage_private(&key_path, self.key_pem.as_bytes()).map_err(|e| identity_error(&key_path, e))?;
+        let cert_tmp = stage_private(&cert_path, self.cert_pem.as_bytes()).map_err(|e| identity_error(&cert_path, e))?;
+
+        fs::rename(&key_tmp, &key_path).map_err(|e| identity_error(&key_path, e))?;
+        fs::rename(&cert_tmp, &cert_path).map_err(|e| identity_error(&cert_path, e))?;
+        Ok(())
+    }
+
+    /// Reusa o certificado da conta ou gera e salva um novo
+    pub fn load_or_create(store: &AccountStore, account: &str) -> Result<Self, ConnectError> {
+        if let Some(identity) = Self::load(store, account)? {
+            return Ok(identity);
+        }
+
+        let identity = Self::generate()?;
+        identity.save(store, account)?;
+        info!("🔏 Novo certificado de cliente salvo para a conta {} ({})", account, identity.fingerprint);
+        Ok(identity)
+    }
+
+    /// Troca o certificado da conta por um novo; tokens presos ao antigo deixam de servir
+    pub fn rotate(store: &AccountStore, account: &str) -> Result<Self, ConnectError> {
+        let identity = Self::generate()?;
+        identity.save(store, account)?;
+        Ok(identity)
+    }
+
+    /// Fingerprint `x5t#S256` enviado ao session service
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub(crate) fn cert_chain(&self) -> Vec<CertificateDer<'static>> {
+        vec![self.cert.clone()]
+    }
+
+    pub(crate) fn private_key(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(self.key.clone_key())
+    }
+}
+
+fn paths(store: &AccountStore, account: &str) -> Result<(PathBuf, PathBuf), ConnectError> {
+    let dir = store.account_dir(account)
+        .map_err(|e| identity_error(&store.root().join(account), e))?;
+    Ok((dir.join(CERT_FILE), dir.join(KEY_FILE)))
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>, ConnectError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(identity_error(path, e)),
+    }
+}
+
+fn missing_half() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "o outro arquivo do par certificado/chave existe, este não")
+}
+
+fn invalid_pem(error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("PEM inválido: {}", error))
+}
+
+fn identity_error(path: &Path, source: io::Error) -> ConnectError {
+    ConnectError::Identity { path: path.to_path_buf(), source }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_uuid;
+
+    /// Conta `bot` numa pasta descartável, apagada quando o teste termina
+    struct TempStore(AccountStore);
+
+    impl TempStore {
+        fn new() -> Self {
+            Self(AccountStore::new(std::env::temp_dir().join(format!("hytale-bot-identity-{}", random_uuid()))))
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.root());
+        }
+    }
+
+    #[test]
+    fn saved_pair_loads_back() {
+        let store = TempStore::new();
+        let identity = ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+
+        let loaded = ClientIdentity::load(&store.0, "bot").unwrap().unwrap();
+        assert_eq!(loaded.fingerprint(), identity.fingerprint());
+    }
+
+    #[test]
+    fn key_from_another_pair_is_regenerated() {
+        let store = TempStore::new();
+        let old = ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+
+        // Queda depois de trocar a chave e antes de trocar o certificado
+        let (_, key_path) = paths(&store.0, "bot").unwrap();
+        fs::write(&key_path, ClientIdentity::generate().unwrap().key_pem).unwrap();
+
+        assert!(ClientIdentity::load(&store.0, "bot").unwrap().is_none());
+        let new = ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+        assert_ne!(new.fingerprint(), old.fingerprint());
+        assert_eq!(ClientIdentity::load(&store.0, "bot").unwrap().unwrap().fingerprint(), new.fingerprint());
+    }
+
+    #[test]
+    fn key_without_certificate_is_regenerated() {
+        let store = TempStore::new();
+        ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+        let (cert_path, _) = paths(&store.0, "bot").unwrap();
+        fs::remove_file(&cert_path).unwrap();
+
+        assert!(ClientIdentity::load(&store.0, "bot").unwrap().is_none());
+        ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+        assert!(ClientIdentity::load(&store.0, "bot").unwrap().is_some());
+    }
+
+    #[test]
+    fn certificate_without_key_is_an_error() {
+        let store = TempStore::new();
+        ClientIdentity::load_or_create(&store.0, "bot").unwrap();
+        let (_, key_path) = paths(&store.0, "bot").unwrap();
+        fs::remove_file(&key_path).unwrap();
+
+        assert!(ClientIdentity::load(&store.0, "bot").is_err());
+    }
+}
@@ -0,0 +1,15 @@
+//! Hytale bot client library
+
+// Permite que o código gerado por `#[derive(HytalePacket)]` use `::hytale_bot::...` aqui dentro também
+extern crate self as hytale_bot;
+
+pub mod accounts;
+pub mod auth;
+pub mod error;
+pub mod identity;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod net;
+pub mod protocol;
+pub mod trust;
+pub mod utils;
@@ -1,72 +1,600 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use clap::{Args, Parser, Subcommand};
+use hytale_bot::accounts::{self, AccountStore};
+use hytale_bot::identity::ClientIdentity;
+use hytale_bot::protocol::ProtocolError;
+use hytale_bot::trust::{KnownServers, ServerTrust};
+use hytale_bot::utils::random_uuid;
+use hytale_bot::{auth, net, protocol};
 use quinn::Endpoint;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
 use uuid::Uuid;
 
-mod auth;
-mod net;
-mod protocol;
-mod utils;
+// ============================================================================
+// Linha de comando
+// ============================================================================
 
-const PORT: u16 = 5520;
-const SERVER_ADDRESS: &str = "72.60.149.222";
-const USERNAME: &str = "SEU_NOME_AQUI";
-const UUID: &str = "SUA_UUID_AQUI"; // Exemplo: "123e4567-e89b-12d3-a456-426614174000"
+/// Bot cliente para servidores Hytale
+#[derive(Parser)]
+#[command(name = "hytale-bot", version)]
+struct Cli {
+    /// Nível de log (off, error, warn, info, debug, trace)
+    #[arg(long, global = true, default_value = "info")]
+    log_level: LevelFilter,
+
+    /// Pasta com contas, tokens, certificados e servidores conhecidos
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Faz o login OAuth e salva os tokens da conta
+    Login(LoginArgs),
+    /// Entra no servidor e fica no mundo até o Ctrl-C
+    Connect(ConnectArgs),
+    /// Só o handshake, mostrando o tempo de cada etapa
+    Ping(PingArgs),
+    /// Contas salvas na pasta de configuração
+    #[command(subcommand)]
+    Accounts(AccountsCommand),
+}
+
+#[derive(Subcommand)]
+enum AccountsCommand {
+    /// Lista as contas com o estado dos tokens e o certificado de cada uma
+    List,
+    /// Troca o certificado de cliente da conta por um novo
+    RotateCert {
+        /// Nome da conta
+        account: String,
+    },
+}
+
+#[derive(Args)]
+struct AccountArgs {
+    /// Nome da conta no cache de tokens
+    #[arg(long, default_value = "default")]
+    account: String,
+
+    /// Sem navegador: imprime a URL e lê o código (ou a URL de redirect) do stdin
+    #[arg(long)]
+    headless: bool,
+
+    /// Lê o código (ou a URL de redirect) deste arquivo
+    #[arg(long, value_name = "FILE", conflicts_with = "headless")]
+    code_file: Option<PathBuf>,
+}
+
+impl AccountArgs {
+    fn login_mode(&self) -> auth::LoginMode {
+        match (&self.code_file, self.headless) {
+            (Some(path), _) => auth::LoginMode::File(path.clone()),
+            (None, true) => auth::LoginMode::Stdin,
+            (None, false) => auth::LoginMode::Browser,
+        }
+    }
+}
+
+#[derive(Args)]
+struct LoginArgs {
+    #[command(flatten)]
+    account: AccountArgs,
+
+    /// Descarta os tokens salvos e faz o login de novo
+    #[arg(long)]
+    force: bool,
+
+    /// Cadastra a conta com um refresh token já existente (ou `@arquivo` com ele), sem OAuth
+    #[arg(long, value_name = "TOKEN|@FILE", conflicts_with_all = ["force", "headless", "code_file"])]
+    refresh_token: Option<String>,
+}
+
+#[derive(Args)]
+struct ServerArgs {
+    /// Servidor no formato host[:porta] (porta padrão 5520)
+    host: net::ServerAddress,
+
+    /// Versão ou hash do protocolo; sem ela tenta todas as conhecidas
+    #[arg(long)]
+    protocol: Option<String>,
+
+    /// Fingerprint x5t#S256 esperado do certificado do servidor; sem ele vale o primeiro visto
+    #[arg(long, value_name = "FINGERPRINT")]
+    server_fingerprint: Option<String>,
+
+    /// Aceita qualquer certificado do servidor (a conexão pode ser interceptada)
+    #[arg(long, conflicts_with = "server_fingerprint")]
+    dangerous_skip_server_verification: bool,
+
+    #[command(flatten)]
+    transport: TransportArgs,
+}
+
+/// Ajustes do QUIC; o que não for passado fica com o padrão de `net::TransportOptions`
+#[derive(Args)]
+struct TransportArgs {
+    /// Segundos sem nenhum pacote até a conexão ser dada como morta
+    #[arg(long, value_name = "SECS")]
+    idle_timeout: Option<u64>,
+
+    /// Intervalo dos keep-alives em segundos, menor que o idle timeout (0 desliga)
+    #[arg(long, value_name = "SECS")]
+    keep_alive: Option<u64>,
+
+    /// Tamanho inicial dos datagramas UDP
+    #[arg(long, value_name = "BYTES")]
+    initial_mtu: Option<u16>,
+
+    /// Mantém o MTU inicial em vez de descobrir um maior
+    #[arg(long)]
+    no_mtu_discovery: bool,
+
+    /// Bytes que o servidor pode mandar num stream sem esperar confirmação
+    #[arg(long, value_name = "BYTES")]
+    stream_receive_window: Option<u64>,
+
+    /// Bytes que o servidor pode mandar na conexão toda sem esperar confirmação
+    #[arg(long, value_name = "BYTES")]
+    receive_window: Option<u64>,
+
+    /// Bytes enviados e ainda não confirmados pelo servidor
+    #[arg(long, value_name = "BYTES")]
+    send_window: Option<u64>,
+
+    /// Controle de congestionamento (BBR ajuda em links com perda ou latência alta)
+    #[arg(long, value_enum)]
+    congestion: Option<net::CongestionController>,
+}
+
+impl TransportArgs {
+    fn options(&self) -> net::TransportOptions {
+        let defaults = net::TransportOptions::default();
+
+        net::TransportOptions {
+            idle_timeout: self.idle_timeout.map_or(defaults.idle_timeout, Duration::from_secs),
+            keep_alive_interval: match self.keep_alive {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.keep_alive_interval,
+            },
+            initial_mtu: self.initial_mtu.unwrap_or(defaults.initial_mtu),
+            mtu_discovery: !self.no_mtu_discovery,
+            stream_receive_window: self.stream_receive_window,
+            receive_window: self.receive_window,
+            send_window: self.send_window,
+            congestion_controller: self.congestion.unwrap_or(defaults.congestion_controller),
+        }
+    }
+}
+
+impl ServerArgs {
+    fn trust(&self, config_dir: &Path) -> Result<ServerTrust, Box<dyn std::error::Error>> {
+        if self.dangerous_skip_server_verification {
+            warn!("⚠️ Verificação do certificado do servidor desligada: a conexão pode ser interceptada");
+            return Ok(ServerTrust::DangerousAcceptAny);
+        }
+
+        Ok(match &self.server_fingerprint {
+            Some(fingerprint) => ServerTrust::Pinned(fingerprint.clone()),
+            None => ServerTrust::TrustOnFirstUse(Arc::new(KnownServers::load(KnownServers::path_in(config_dir))?)),
+        })
+    }
+}
+
+#[derive(Args)]
+struct ConnectArgs {
+    #[command(flatten)]
+    server: ServerArgs,
+
+    #[command(flatten)]
+    account: AccountArgs,
+
+    /// Nome do bot no servidor
+    #[arg(short, long)]
+    username: String,
+
+    /// UUID do perfil da conta (sem `--insecure` é obrigatória)
+    #[arg(long, required_unless_present = "insecure")]
+    uuid: Option<Uuid>,
+
+    /// Servidor em --auth-mode insecure: entra sem conta Hytale
+    #[arg(long)]
+    insecure: bool,
+
+    /// Senha de servidores privados
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Gera um certificado descartável em vez de reusar o salvo na conta
+    #[arg(long)]
+    ephemeral_identity: bool,
+}
+
+#[derive(Args)]
+struct PingArgs {
+    #[command(flatten)]
+    server: ServerArgs,
+
+    /// Nome enviado no Connect
+    #[arg(short, long, default_value = "ping")]
+    username: String,
+}
+
+// ============================================================================
+// Ctrl-C
+// ============================================================================
+
+/// Ctrl-C anotado num canal, para cada etapa decidir como encerrar
+struct Interrupt(watch::Receiver<bool>);
+
+impl Interrupt {
+    /// Troca o comportamento padrão (matar o processo) pelo aviso no canal
+    fn install() -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                let _ = tx.send(true);
+            }
+        });
+        Self(rx)
+    }
+
+    /// `None` se o Ctrl-C chegar (ou já tiver chegado) antes de `future` terminar
+    async fn race<F: Future>(&mut self, future: F) -> Option<F::Output> {
+        tokio::select! {
+            output = future => Some(output),
+            _ = Self::received(&mut self.0) => None,
+        }
+    }
+
+    async fn received(rx: &mut watch::Receiver<bool>) {
+        // Sem handler de sinal o Ctrl-C nunca vai chegar
+        if rx.wait_for(|&received| received).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+// ============================================================================
+// Comandos
+// ============================================================================
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    tracing_subscriber::fmt().with_max_level(cli.log_level).init();
+
+    let config_dir = cli.config_dir.unwrap_or_else(accounts::config_root);
+
+    let result = match cli.command {
+        Command::Login(args) => login(&config_dir, &args).await,
+        Command::Connect(args) => connect(&config_dir, &args).await,
+        Command::Ping(args) => ping(&config_dir, &args).await,
+        Command::Accounts(AccountsCommand::List) => list_accounts(&config_dir),
+        Command::Accounts(AccountsCommand::RotateCert { account }) => rotate_cert(&config_dir, &account),
+    };
+
+    // Display em vez do Debug que `main -> Result` imprimiria
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn login(config_dir: &Path, args: &LoginArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = auth::TokenCache::new(AccountStore::in_config_dir(config_dir));
+    let account = &args.account.account;
 
-    let server_address = format!("{}:{}", SERVER_ADDRESS, PORT);
-    let username = USERNAME;
-    let uuid: Uuid = Uuid::parse_str(UUID)?;
+    if args.force {
+        cache.remove(account)?;
+    }
+
+    let auth_client = auth::AuthClient::new(auth::AuthEndpoints::production());
+
+    // Token de outra máquina: o arquivo evita deixá-lo no histórico do shell
+    if let Some(refresh_token) = &args.refresh_token {
+        let refresh_token = match refresh_token.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path).map_err(|e| format!("Falha ao ler {}: {}", path, e))?,
+            None => refresh_token.clone(),
+        };
+        auth::import_refresh_token(&auth_client, &cache, account, &refresh_token).await?;
+
+        info!("✅ Refresh token importado, tokens salvos na conta {}", account);
+        return Ok(());
+    }
+
+    auth::login(&auth_client, &cache, account, &args.account.login_mode()).await?;
+
+    info!("✅ Login concluído, tokens salvos na conta {}", account);
+    Ok(())
+}
+
+async fn connect(config_dir: &Path, args: &ConnectArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let store = AccountStore::in_config_dir(config_dir);
+    let account = &args.account.account;
 
-    println!("Iniciando autenticação...");
+    // Certificado do mTLS; o access token fica preso ao fingerprint dele
+    let identity = if args.ephemeral_identity {
+        ClientIdentity::generate()?
+    } else {
+        ClientIdentity::load_or_create(&store, account)?
+    };
+    let trust = args.server.trust(config_dir)?;
 
-    // 1. Obter Token de Acesso (Login Web)
-    let token_data = auth::get_access_token().await?;
+    // Servidores de desenvolvimento não validam tokens, login e sessão são dispensados
+    if args.insecure {
+        warn!("⚠️ Modo insecure: só funciona em servidores com --auth-mode insecure");
+        let uuid = args.uuid.unwrap_or_else(random_uuid);
+        return run(None, &identity, trust, args, uuid, &mut Interrupt::install()).await;
+    }
+    let uuid = args.uuid.ok_or("--uuid é obrigatória fora do modo insecure")?;
+
+    info!("Iniciando autenticação...");
+
+    // 1. Obter Token de Acesso (cache, refresh_token ou login web)
+    //    Um Ctrl-C aqui ainda mata o processo: não há sessão para encerrar e a leitura
+    //    do stdin no login headless seguraria o runtime aberto
+    let auth_client = auth::AuthClient::new(auth::AuthEndpoints::production());
+    let cache = auth::TokenCache::new(store);
+    let token_data = auth::login(&auth_client, &cache, account, &args.account.login_mode()).await?;
     let access_token = &token_data.access_token;
 
-    // 2. Criar Sessão de Jogo
-    let session_response = auth::create_game_session(access_token, uuid).await?;
+    // A partir daqui o Ctrl-C só é anotado, para a sessão sempre ser encerrada abaixo
+    let mut interrupt = Interrupt::install();
+
+    // 2. Criar Sessão de Jogo (renovada em background enquanto o bot roda)
+    let session = auth::GameSession::create(&auth_client, access_token, uuid).await?;
+    let refresher = session.spawn_refresher();
+
+    let result = run(Some(&session), &identity, trust, args, uuid, &mut interrupt).await;
+
+    // Sessão encerrada no serviço para os tokens não ficarem válidos à toa
+    refresher.abort();
+    if let Err(e) = session.delete().await {
+        warn!("⚠️ Falha ao encerrar sessão de jogo: {}", e);
+    }
+
+    result
+}
+
+/// Conecta, autentica e mantém o bot no mundo até desconectar ou o Ctrl-C
+///
+/// Sem sessão de jogo o bot usa o fluxo de desenvolvimento (`--auth-mode insecure`).
+async fn run(
+    session: Option<&auth::GameSession>,
+    identity: &ClientIdentity,
+    trust: ServerTrust,
+    args: &ConnectArgs,
+    uuid: Uuid,
+    interrupt: &mut Interrupt,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let server_address = &args.server.host;
 
     // 3. Conectar ao Servidor de Jogo
-    let (config, x509_fingerprint) = net::configure_client();
-
-    let mut game_client = Endpoint::client("[::]:0".parse()?)?;
-    game_client.set_default_client_config(config);
-
-    println!("Conectando ao servidor de jogo...");
-    let connection = game_client
-        .connect(server_address.parse()?, "hytale_server")?
-        .await
-        .map_err(|e| format!("Falha ao conectar ao servidor de jogo: {}", e))?;
-
-    match connection.open_bi().await {
-        Ok((mut send, mut recv)) => {
-            let packet = protocol::build_connect_packet_with_token(
-                username,
-                uuid,
-                &session_response.identityToken,
-            );
-            send.write_all(&packet).await?;
-
-            if let Err(e) = protocol::handle_auth_flow_network(
-                &mut send,
-                &mut recv,
-                &session_response.identityToken,
-                &session_response.sessionToken,
-                &x509_fingerprint,
-            )
-            .await
-            {
-                println!("Erro durante autenticação: {}", e);
-            } else {
-                println!("Autenticação concluída com sucesso!");
+    let config = net::configure_client(identity, server_address, trust, &args.server.transport.options())?;
+
+    // Versão fixa ou, se o servidor recusar por protocolo, as mais antigas em sequência
+    let versions = protocol::versions::candidate_versions(args.server.protocol.as_deref())?;
+    let mut authenticated = None;
+
+    for (i, &version) in versions.iter().enumerate() {
+        info!("Conectando a {} (protocolo {})...", server_address, version.version);
+        let tokens = match session {
+            Some(session) => {
+                session.ensure_fresh().await?;
+                Some(session.tokens())
             }
+            None => None,
+        };
+
+        // Antes do stream do protocolo não há a quem mandar o Disconnect
+        let Some(connected) = interrupt.race(net::connect(&config, server_address)).await else {
+            info!("👋 Ctrl-C recebido, cancelando a conexão");
+            return Ok(());
+        };
+        let (game_client, quic) = connected?;
+
+        let mut connection = protocol::Connection::open(quic, version).await?;
+        if let Some(password) = &args.password {
+            connection.set_password(password.as_str());
         }
-        Err(e) => {
-            println!("Falha ao abrir canal bidirecional: {}", e);
+
+        let joined = interrupt
+            .race(authenticate(&mut connection, identity, &args.username, uuid, session.zip(tokens.as_ref())))
+            .await;
+
+        match joined {
+            Some(Ok(())) => {
+                authenticated = Some((game_client, connection));
+                break;
+            }
+            Some(Err(_)) if connection.rejected_as_outdated() && i + 1 < versions.len() => {
+                warn!("⚠️ Servidor recusou o protocolo {}, tentando versão anterior...", version.version);
+            }
+            Some(Err(e)) => {
+                close(&mut connection, &game_client).await;
+                return Err(format!("Erro durante autenticação: {}", e).into());
+            }
+            None => return shutdown(&mut connection, &game_client).await,
+        }
+    }
+
+    let Some((game_client, mut connection)) = authenticated else {
+        return Err("Nenhuma versão do protocolo para tentar".into());
+    };
+    info!("Autenticação concluída com sucesso!");
+
+    // 4. Fase de Setup até o JoinWorld
+    match interrupt.race(connection.setup()).await {
+        Some(Ok(_)) => info!("✅ Bot entrou no mundo!"),
+        Some(Err(e)) => {
+            close(&mut connection, &game_client).await;
+            return Err(format!("Erro durante o Setup: {}", e).into());
+        }
+        None => return shutdown(&mut connection, &game_client).await,
+    }
+
+    // 5. Permanecer no mundo
+    match interrupt.race(connection.play()).await {
+        Some(Ok(())) => Ok(()),
+        Some(Err(e)) => {
+            let phase = connection.phase();
+            close(&mut connection, &game_client).await;
+            Err(format!("Conexão encerrada (fase {:?}): {}", phase, e).into())
+        }
+        None => shutdown(&mut connection, &game_client).await,
+    }
+}
+
+/// Connect e autenticação, com os tokens da sessão ou pelo fluxo de desenvolvimento
+async fn authenticate(
+    connection: &mut protocol::Connection,
+    identity: &ClientIdentity,
+    username: &str,
+    uuid: Uuid,
+    session: Option<(&auth::GameSession, &auth::SessionTokens)>,
+) -> Result<(), ProtocolError> {
+    connection
+        .handshake(username, uuid, session.map(|(_, tokens)| tokens.identity_token.as_str()))
+        .await?;
+
+    match session {
+        Some((session, tokens)) => {
+            connection
+                .authenticate(session.client(), &tokens.identity_token, &tokens.session_token, identity.fingerprint())
+                .await
+        }
+        None => connection.authenticate_insecure().await,
+    }
+}
+
+/// Ctrl-C: envia o Disconnect e espera o fechamento da conexão chegar ao servidor
+async fn shutdown(connection: &mut protocol::Connection, endpoint: &Endpoint) -> Result<(), Box<dyn std::error::Error>> {
+    info!("👋 Ctrl-C recebido, desconectando...");
+
+    if let Err(e) = connection.disconnect("Client disconnected").await {
+        warn!("⚠️ Falha ao enviar Disconnect: {}", e);
+    }
+    endpoint.wait_idle().await;
+
+    Ok(())
+}
+
+/// Encerramento depois de um erro; a conexão costuma já ter caído, então o Disconnect é só uma tentativa
+async fn close(connection: &mut protocol::Connection, endpoint: &Endpoint) {
+    let _ = connection.disconnect("Client error").await;
+    endpoint.wait_idle().await;
+}
+
+/// DNS, handshake QUIC e a primeira resposta ao Connect, sem login nem sessão
+///
+/// Sem identity token, servidores autenticados respondem ao Connect com um Disconnect,
+/// o que já basta para medir o tempo.
+async fn ping(config_dir: &Path, args: &PingArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let server_address = &args.server.host;
+    let version = protocol::versions::candidate_versions(args.server.protocol.as_deref())?[0];
+
+    // O servidor não amarra nada ao certificado antes da autenticação
+    let identity = ClientIdentity::generate()?;
+    let trust = args.server.trust(config_dir)?;
+    let config = net::configure_client(&identity, server_address, trust, &args.server.transport.options())?;
+
+    let started = Instant::now();
+    let addrs = server_address.resolve().await?;
+    info!("🔎 {} resolvido em {:?}: {:?}", server_address, started.elapsed(), addrs);
+
+    let started = Instant::now();
+    let (endpoint, quic) = net::connect(&config, server_address).await?;
+    info!("🔗 QUIC conectado a {} em {:?} (RTT {:?})", quic.remote_address(), started.elapsed(), quic.rtt());
+
+    let mut connection = protocol::Connection::open(quic, version).await?;
+    let started = Instant::now();
+    let response = match connection.handshake(&args.username, random_uuid(), None).await {
+        Ok(()) => connection.authenticate_insecure().await,
+        Err(e) => Err(e),
+    };
+    let elapsed = started.elapsed();
+
+    // Pedir autenticação ou senha, ou recusar o Connect, também é resposta; só os outros erros falham o ping
+    let result = match response {
+        Ok(()) => {
+            info!("✅ Connect aceito em {:?} (servidor em modo insecure)", elapsed);
+            Ok(())
+        }
+        Err(ProtocolError::AuthRequired) => {
+            info!("✅ Servidor respondeu em {:?} pedindo autenticação", elapsed);
+            Ok(())
+        }
+        Err(ProtocolError::PasswordRequired) => {
+            info!("✅ Servidor respondeu em {:?} pedindo senha", elapsed);
+            Ok(())
+        }
+        Err(ProtocolError::ServerDisconnect { reason, .. }) => {
+            info!("✅ Servidor respondeu em {:?} com Disconnect: {}", elapsed, reason);
+            Ok(())
         }
+        Err(e) => Err(format!("Resposta ao Connect em {:?} (protocolo {}): {}", elapsed, version.version, e).into()),
+    };
+
+    if let Err(e) = connection.disconnect("Ping").await {
+        warn!("⚠️ Falha ao enviar Disconnect: {}", e);
     }
+    endpoint.wait_idle().await;
+
+    result
+}
 
+fn list_accounts(config_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let store = AccountStore::in_config_dir(config_dir);
+    let accounts = store.list()?;
+
+    if accounts.is_empty() {
+        println!("Nenhuma conta salva em {}", store.root().display());
+        return Ok(());
+    }
+
+    let cache = auth::TokenCache::new(store.clone());
+    for account in accounts {
+        let tokens = match cache.load(&account) {
+            Ok(Some(tokens)) if tokens.is_valid() => "token válido".to_string(),
+            Ok(Some(tokens)) if tokens.refresh_token.is_some() => "token expirado (renovável)".to_string(),
+            Ok(Some(_)) => "token expirado".to_string(),
+            Ok(None) => "sem tokens".to_string(),
+            Err(e) => format!("erro: {}", e),
+        };
+        let certificate = match ClientIdentity::load(&store, &account) {
+            Ok(Some(identity)) => identity.fingerprint().to_string(),
+            Ok(None) => "sem certificado".to_string(),
+            Err(e) => format!("erro: {}", e),
+        };
+
+        println!("{:<20} {:<28} {}", account, tokens, certificate);
+    }
+
+    Ok(())
+}
+
+fn rotate_cert(config_dir: &Path, account: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = AccountStore::in_config_dir(config_dir);
+    // Um par corrompido também se resolve trocando, então o erro de leitura não impede
+    let old = ClientIdentity::load(&store, account).ok().flatten();
+    let identity = ClientIdentity::rotate(&store, account)?;
+
+    match old {
+        Some(old) => println!("🔏 Certificado da conta {} trocado: {} -> {}", account, old.fingerprint(), identity.fingerprint()),
+        None => println!("🔏 Certificado criado para a conta {}: {}", account, identity.fingerprint()),
+    }
     Ok(())
 }
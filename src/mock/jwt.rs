@@ -0,0 +1,79 @@
+//! EdDSA JWT signing for the mock services
+
+use std::sync::Arc;
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde_json::{json, Value};
+use crate::utils::unix_now;
+
+/// Chave Ed25519 que assina todos os tokens emitidos pelos mocks
+///
+/// Gerada a cada execução: tokens de um mock não valem em outro.
+#[derive(Clone)]
+pub struct MockSigner {
+    key: Arc<SigningKey>,
+    key_id: String,
+}
+
+impl MockSigner {
+    pub fn generate() -> Self {
+        let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let key_id = hex_prefix(key.verifying_key().as_bytes());
+
+        Self { key: Arc::new(key), key_id }
+    }
+
+    /// Assina os claims como um JWT compacto (`header.payload.signature`)
+    pub fn sign(&self, claims: &Value) -> String {
+        let header = json!({ "alg": "EdDSA", "typ": "JWT", "kid": self.key_id });
+        let signing_input = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(header.to_string()),
+            general_purpose::URL_SAFE_NO_PAD.encode(claims.to_string()),
+        );
+
+        let signature = self.key.sign(signing_input.as_bytes());
+        format!("{}.{}", signing_input, general_purpose::URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+
+    /// Claims do token se a assinatura for desta chave e ele ainda não expirou
+    pub fn verify(&self, token: &str) -> Option<Value> {
+        let (signing_input, signature_b64) = token.rsplit_once('.')?;
+        let (_, payload_b64) = signing_input.split_once('.')?;
+
+        let signature_bytes = general_purpose::URL_SAFE_NO_PAD.decode(signature_b64).ok()?;
+        let signature = Signature::from_slice(&signature_bytes).ok()?;
+        self.key.verifying_key().verify(signing_input.as_bytes(), &signature).ok()?;
+
+        let payload = general_purpose::URL_SAFE_NO_PAD.decode(payload_b64).ok()?;
+        let claims: Value = serde_json::from_slice(&payload).ok()?;
+
+        let expired = claims.get("exp").and_then(Value::as_u64).is_some_and(|exp| exp <= unix_now());
+        (!expired).then_some(claims)
+    }
+
+    /// Chave pública no formato JWKS, como o session service publica
+    pub fn jwks(&self) -> Value {
+        json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "alg": "EdDSA",
+                "use": "sig",
+                "kid": self.key_id,
+                "x": general_purpose::URL_SAFE_NO_PAD.encode(self.key.verifying_key().as_bytes()),
+            }]
+        })
+    }
+}
+
+impl std::fmt::Debug for MockSigner {
+    // A chave privada não aparece em logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockSigner").field("key_id", &self.key_id).finish()
+    }
+}
+
+fn hex_prefix(bytes: &[u8]) -> String {
+    bytes.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,9 @@
+//! Local stand-ins for the Hytale services, for offline end-to-end tests
+
+pub mod jwt;
+pub mod server;
+pub mod session;
+
+pub use jwt::MockSigner;
+pub use server::{Action, MockGameServer, MockServerConfig, ServerEvent, Stage};
+pub use session::{MockAuthority, MockSessionConfig, MockSessionService};
@@ -0,0 +1,614 @@
+//! Scriptable mock Hytale game server over QUIC
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use quinn::{Endpoint, RecvStream, SendStream};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{DigitallySignedStruct, DistinguishedName, SignatureScheme};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::protocol::*;
+use crate::utils::{fingerprints_match, random_uuid, x509_fingerprint};
+use super::session::MockAuthority;
+
+/// Quanto esperar o cliente fechar a conexão depois de um Disconnect do servidor
+const DISCONNECT_GRACE: Duration = Duration::from_secs(2);
+
+// ============================================================================
+// Script
+// ============================================================================
+
+/// Ponto do fluxo em que as ações do script rodam
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Connect recebido, antes de qualquer resposta
+    Connected,
+    /// AuthToken recebido, antes do ServerAuthToken (ou do ConnectAccept no modo insecure)
+    Authenticating,
+    /// Autenticação concluída, antes do WorldSettings
+    Setup,
+    /// ClientReady recebido, jogador no mundo
+    Playing,
+}
+
+/// O que o servidor faz num ponto do fluxo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Ping com este ID, sem esperar os Pongs (registrados como eventos)
+    Ping(u32),
+    /// Disconnect com o motivo, encerrando a sessão
+    Disconnect(String),
+    /// Frame com ID e payload arbitrários, ex: um pacote malformado
+    Raw { packet_id: u32, payload: Vec<u8> },
+    /// Para de mandar e de ler pacotes por este tempo
+    Stall(Duration),
+    /// Fecha a conexão QUIC sem Disconnect
+    Close,
+}
+
+/// Como o servidor falso se comporta
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    /// Hash aceito no Connect; qualquer outro recebe Disconnect de cliente desatualizado
+    pub protocol_hash: String,
+    /// `--auth-mode insecure`: responde o Connect direto com ConnectAccept
+    pub insecure: bool,
+    /// Valida e emite tokens como o session service; None aceita qualquer token
+    pub authority: Option<MockAuthority>,
+    /// `sub` do identity token do servidor
+    pub server_uuid: Uuid,
+    /// Manda o identity token do servidor no AuthGrant; sem ele o cliente não tem para quem
+    /// gerar o grant da autenticação mútua
+    pub server_identity: bool,
+    /// Senha pedida depois da autenticação
+    pub password: Option<String>,
+    /// Tentativas de senha antes do Disconnect
+    pub password_attempts: i32,
+    pub script: Vec<(Stage, Action)>,
+}
+
+impl MockServerConfig {
+    /// Agenda uma ação; ações do mesmo ponto rodam na ordem em que foram agendadas
+    pub fn on(&mut self, stage: Stage, action: Action) -> &mut Self {
+        self.script.push((stage, action));
+        self
+    }
+
+    fn actions(&self, stage: Stage) -> impl Iterator<Item = &Action> {
+        self.script.iter().filter(move |(s, _)| *s == stage).map(|(_, action)| action)
+    }
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            protocol_hash: String::from_utf8_lossy(PROTOCOL_HASH).into_owned(),
+            insecure: false,
+            authority: None,
+            server_uuid: random_uuid(),
+            server_identity: true,
+            password: None,
+            password_attempts: 3,
+            script: Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// Eventos
+// ============================================================================
+
+/// O que o servidor viu, para os testes conferirem depois
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerEvent {
+    Connected { username: String, uuid: Uuid, identity_token: Option<String> },
+    /// Qualquer pacote recebido do cliente, com o ID da versão atual
+    Packet { packet_id: u32 },
+    Pong { id: u32, pong_type: PongType },
+    PasswordAttempt { accepted: bool },
+    Authenticated,
+    JoinedWorld,
+    /// Disconnect enviado pelo cliente
+    ClientDisconnected { reason: String },
+    /// Sessão encerrada; `error` vazio se terminou normalmente
+    Closed { error: Option<String> },
+}
+
+#[derive(Clone, Default)]
+struct EventLog {
+    events: Arc<Mutex<Vec<ServerEvent>>>,
+    notify: Arc<Notify>,
+}
+
+impl EventLog {
+    fn push(&self, event: ServerEvent) {
+        self.events.lock().unwrap().push(event);
+        self.notify.notify_waiters();
+    }
+}
+
+// ============================================================================
+// Servidor
+// ============================================================================
+
+/// Servidor de jogo falso em 127.0.0.1, com mTLS e ALPN `hytale/1` como o real
+///
+/// Fala Connect → AuthGrant → AuthToken → ServerAuthToken (ou ConnectAccept no modo
+/// insecure), a senha se configurada e o Setup até o JoinWorld, rodando as ações do
+/// script em cada ponto do fluxo.
+pub struct MockGameServer {
+    endpoint: Endpoint,
+    addr: SocketAddr,
+    fingerprint: String,
+    events: EventLog,
+    acceptor: JoinHandle<()>,
+}
+
+impl MockGameServer {
+    pub async fn start(config: MockServerConfig) -> io::Result<Self> {
+        let (server_config, server_fingerprint) = server_config()?;
+        let endpoint = Endpoint::server(server_config, (std::net::Ipv4Addr::LOCALHOST, 0).into())?;
+        let addr = endpoint.local_addr()?;
+        let events = EventLog::default();
+
+        let fingerprint = server_fingerprint.clone();
+        let shared = Arc::new(Shared { config, server_fingerprint, events: events.clone() });
+        let acceptor = tokio::spawn(accept_loop(endpoint.clone(), shared));
+
+        Ok(Self { endpoint, addr, fingerprint, events, acceptor })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Fingerprint `x5t#S256` do certificado do servidor, para fixar no cliente
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub fn events(&self) -> Vec<ServerEvent> {
+        self.events.events.lock().unwrap().clone()
+    }
+
+    /// Espera um evento que satisfaça `matches`, ou None depois de `limit`
+    pub async fn wait_for(&self, limit: Duration, matches: impl Fn(&ServerEvent) -> bool) -> Option<ServerEvent> {
+        tokio::time::timeout(limit, async {
+            loop {
+                // Registrado antes de olhar os eventos para não perder um push no meio
+                let notified = self.events.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                if let Some(event) = self.events().into_iter().find(|e| matches(e)) {
+                    return event;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .ok()
+    }
+}
+
+impl Drop for MockGameServer {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+        self.endpoint.close(quinn::VarInt::from_u32(0), b"mock server shutdown");
+    }
+}
+
+/// Config TLS/QUIC do servidor e o fingerprint do seu certificado
+fn server_config() -> io::Result<(quinn::ServerConfig, String)> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let certified_key = rcgen::generate_simple_self_signed(vec!["hytale_server".to_string()])
+        .map_err(io::Error::other)?;
+    let cert_der = certified_key.cert.der().clone();
+    let server_fingerprint = x509_fingerprint(&cert_der);
+    let key_der = PrivateKeyDer::Pkcs8(certified_key.signing_key.serialize_der().into());
+
+    let mut tls_config = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(io::Error::other)?
+        .with_client_cert_verifier(Arc::new(AnyClientCert { provider }))
+        .with_single_cert(vec![cert_der], key_der)
+        .map_err(io::Error::other)?;
+    tls_config.alpn_protocols = vec![b"hytale/1".to_vec()];
+
+    let quic_crypto = quinn::crypto::rustls::QuicServerConfig::try_from(tls_config)
+        .map_err(io::Error::other)?;
+    Ok((quinn::ServerConfig::with_crypto(Arc::new(quic_crypto)), server_fingerprint))
+}
+
+/// Exige certificado do cliente mas aceita qualquer um, como o servidor real
+///
+/// O certificado só é amarrado à conta pelo fingerprint no access token.
+#[derive(Debug)]
+struct AnyClientCert {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ClientCertVerifier for AnyClientCert {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Estado comum a todas as conexões do servidor
+struct Shared {
+    config: MockServerConfig,
+    server_fingerprint: String,
+    events: EventLog,
+}
+
+async fn accept_loop(endpoint: Endpoint, shared: Arc<Shared>) {
+    while let Some(incoming) = endpoint.accept().await {
+        let shared = shared.clone();
+
+        tokio::spawn(async move {
+            let error = match serve(incoming, shared.clone()).await {
+                Ok(()) => None,
+                Err(e) => Some(e.to_string()),
+            };
+            shared.events.push(ServerEvent::Closed { error });
+        });
+    }
+}
+
+async fn serve(incoming: quinn::Incoming, shared: Arc<Shared>) -> Result<(), ProtocolError> {
+    let conn = incoming.await?;
+    let (send, recv) = conn.accept_bi().await?;
+
+    let client_fingerprint = conn.peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+        .and_then(|chain| chain.first().map(|cert| x509_fingerprint(cert)));
+
+    let mut session = Session {
+        shared,
+        conn,
+        send,
+        recv,
+        phase: Phase::Handshake,
+        client_fingerprint,
+    };
+    session.run().await
+}
+
+// ============================================================================
+// Sessão
+// ============================================================================
+
+/// Se a sessão segue depois de um passo do script
+enum Next {
+    Continue,
+    Ended,
+}
+
+struct Session {
+    shared: Arc<Shared>,
+    conn: quinn::Connection,
+    send: SendStream,
+    recv: RecvStream,
+    /// Fase em que o cliente deveria estar, do ponto de vista do servidor
+    phase: Phase,
+    client_fingerprint: Option<String>,
+}
+
+impl Session {
+    async fn run(&mut self) -> Result<(), ProtocolError> {
+        let connect: ConnectPacket = self.expect().await?;
+        self.shared.events.push(ServerEvent::Connected {
+            username: connect.username.clone(),
+            uuid: connect.uuid,
+            identity_token: connect.identity_token.clone(),
+        });
+        self.phase = Phase::Authenticating;
+
+        if connect.protocol_hash != self.shared.config.protocol_hash {
+            return self.disconnect("Incompatible protocol hash, please update your client").await;
+        }
+        if let Next::Ended = self.run_stage(Stage::Connected).await? {
+            return Ok(());
+        }
+
+        let challenge = self.shared.config.password.as_ref().map(|_| rand::random::<[u8; 32]>().to_vec());
+        let authenticated = if self.shared.config.insecure {
+            self.development_flow(challenge.clone()).await?
+        } else {
+            self.authenticated_flow(&connect, challenge.clone()).await?
+        };
+        if let Next::Ended = authenticated {
+            return Ok(());
+        }
+
+        if let Some(challenge) = challenge
+            && let Next::Ended = self.password_flow(challenge).await?
+        {
+            return Ok(());
+        }
+        self.shared.events.push(ServerEvent::Authenticated);
+        self.phase = Phase::Setup;
+
+        if let Next::Ended = self.run_stage(Stage::Setup).await? {
+            return Ok(());
+        }
+        if let Next::Ended = self.setup_flow().await? {
+            return Ok(());
+        }
+        self.phase = Phase::Playing;
+
+        if let Next::Ended = self.run_stage(Stage::Playing).await? {
+            return Ok(());
+        }
+
+        // Só registra o que o cliente mandar até ele sair
+        loop {
+            if self.next_packet().await?.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    // ========================================================================
+    // Fluxos
+    // ========================================================================
+
+    async fn development_flow(&mut self, challenge: Option<Vec<u8>>) -> Result<Next, ProtocolError> {
+        if let Next::Ended = self.run_stage(Stage::Authenticating).await? {
+            return Ok(Next::Ended);
+        }
+
+        self.send(&ConnectAcceptPacket { password_challenge: challenge }).await?;
+        Ok(Next::Continue)
+    }
+
+    async fn authenticated_flow(&mut self, connect: &ConnectPacket, challenge: Option<Vec<u8>>) -> Result<Next, ProtocolError> {
+        let Some(identity_token) = &connect.identity_token else {
+            return self.end("Authentication required: missing identity token").await;
+        };
+
+        let authority = self.shared.config.authority.clone();
+        let server_uuid = self.shared.config.server_uuid.to_string();
+
+        // Sem autoridade qualquer token serve, e o grant é só um marcador
+        let (grant, server_identity_token) = match &authority {
+            Some(authority) => {
+                let subject = authority.verify(identity_token, "identity")
+                    .and_then(|claims| claims.get("sub").and_then(|v| v.as_str()).map(str::to_string));
+                if subject.as_deref() != Some(connect.uuid.to_string().as_str()) {
+                    return self.end("Invalid token: identity token rejected").await;
+                }
+
+                let grant = authority.issue_auth_grant(identity_token, &server_uuid);
+                let server_identity_token = self.shared.config.server_identity
+                    .then(|| authority.issue_identity_token(self.shared.config.server_uuid, "MockServer"));
+                (grant, server_identity_token)
+            }
+            None => (Some("mock-authorization-grant".to_string()), None),
+        };
+
+        self.send(&AuthGrantPacket {
+            authorization_grant: grant,
+            server_identity_token,
+        })
+        .await?;
+
+        let auth_token: AuthTokenPacket = self.expect().await?;
+        let server_access_token = match &authority {
+            Some(authority) => {
+                if !self.access_token_valid(authority, auth_token.access_token.as_deref(), &server_uuid) {
+                    return self.end("Server authentication failed: invalid access token").await;
+                }
+
+                // Como o servidor real: access token válido sem grant para o servidor não basta
+                let Some(grant) = auth_token.server_authorization_grant.as_deref() else {
+                    return self.end("Mutual authentication required - please update your client").await;
+                };
+
+                // Grant que o cliente gerou para o servidor vira o access token do servidor
+                authority.exchange_auth_grant(grant, &self.shared.server_fingerprint)
+            }
+            None => None,
+        };
+
+        if let Next::Ended = self.run_stage(Stage::Authenticating).await? {
+            return Ok(Next::Ended);
+        }
+
+        self.send(&ServerAuthTokenPacket {
+            server_access_token,
+            password_challenge: challenge,
+        })
+        .await?;
+        Ok(Next::Continue)
+    }
+
+    /// O access token precisa ser para este servidor e do certificado desta conexão
+    fn access_token_valid(&self, authority: &MockAuthority, access_token: Option<&str>, server_uuid: &str) -> bool {
+        let Some(claims) = access_token.and_then(|token| authority.verify(token, "server-access")) else {
+            return false;
+        };
+
+        let audience_ok = claims.get("aud").and_then(|v| v.as_str()) == Some(server_uuid);
+        let bound_fingerprint = claims.pointer("/cnf/x5t#S256").and_then(|v| v.as_str());
+        let bound_ok = bound_fingerprint
+            .zip(self.client_fingerprint.as_deref())
+            .is_some_and(|(bound, client)| fingerprints_match(bound, client));
+        audience_ok && bound_ok
+    }
+
+    async fn password_flow(&mut self, mut challenge: Vec<u8>) -> Result<Next, ProtocolError> {
+        let password = self.shared.config.password.clone().unwrap_or_default();
+        let mut attempts_remaining = self.shared.config.password_attempts;
+
+        loop {
+            let response: PasswordResponsePacket = self.expect().await?;
+            let accepted = response.hash == PasswordResponsePacket::new(&challenge, &password).hash;
+            self.shared.events.push(ServerEvent::PasswordAttempt { accepted });
+
+            if accepted {
+                self.send(&PasswordAcceptedPacket).await?;
+                return Ok(Next::Continue);
+            }
+
+            attempts_remaining -= 1;
+            if attempts_remaining <= 0 {
+                return self.end("Incorrect password").await;
+            }
+
+            challenge = rand::random::<[u8; 32]>().to_vec();
+            self.send(&PasswordRejectedPacket {
+                attempts_remaining,
+                new_challenge: Some(challenge.clone()),
+            })
+            .await?;
+        }
+    }
+
+    async fn setup_flow(&mut self) -> Result<Next, ProtocolError> {
+        self.send(&WorldSettingsPacket { world_height: 320, required_assets: None }).await?;
+        let _: RequestAssetsPacket = self.expect().await?;
+
+        self.send(&WorldLoadFinishedPacket).await?;
+        let _: PlayerOptionsPacket = self.expect().await?;
+
+        self.send(&JoinWorldPacket {
+            clear_world: true,
+            fade_in_out: false,
+            world_uuid: random_uuid(),
+        })
+        .await?;
+        let _: ClientReadyPacket = self.expect().await?;
+
+        self.shared.events.push(ServerEvent::JoinedWorld);
+        Ok(Next::Continue)
+    }
+
+    async fn run_stage(&mut self, stage: Stage) -> Result<Next, ProtocolError> {
+        let shared = self.shared.clone();
+
+        for action in shared.config.actions(stage) {
+            match action {
+                Action::Ping(id) => {
+                    self.send(&PingPacket {
+                        id: *id,
+                        time: None,
+                        last_ping_value_raw: 0,
+                        last_ping_value_direct: 0,
+                        last_ping_value_tick: 0,
+                    })
+                    .await?;
+                }
+                Action::Disconnect(reason) => return self.end(reason).await,
+                Action::Raw { packet_id, payload } => {
+                    self.send.write_all(&frame_packet(*packet_id, payload.clone())).await?;
+                }
+                Action::Stall(duration) => tokio::time::sleep(*duration).await,
+                Action::Close => {
+                    self.conn.close(quinn::VarInt::from_u32(0), b"mock server closed");
+                    return Ok(Next::Ended);
+                }
+            }
+        }
+
+        Ok(Next::Continue)
+    }
+
+    // ========================================================================
+    // I/O
+    // ========================================================================
+
+    async fn send<P: Packet>(&mut self, packet: &P) -> Result<(), ProtocolError> {
+        self.send.write_all(&versions::current().frame(packet)?).await?;
+        Ok(())
+    }
+
+    /// Próximo pacote que não seja Pong, None se o cliente saiu
+    async fn next_packet(&mut self) -> Result<Option<(u32, Vec<u8>)>, ProtocolError> {
+        loop {
+            let (packet_id, payload) = match read_packet(&mut self.recv, versions::current()).await {
+                Ok(packet) => packet,
+                // Cliente fechou a conexão sem Disconnect
+                Err(ProtocolError::Read(_)) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            self.shared.events.push(ServerEvent::Packet { packet_id });
+
+            match packet_id {
+                PACKET_PONG => {
+                    let pong = PongPacket::decode(&payload).ok_or(ProtocolError::Malformed { packet_id })?;
+                    self.shared.events.push(ServerEvent::Pong { id: pong.id, pong_type: pong.pong_type });
+                }
+                PACKET_DISCONNECT => {
+                    let disconnect = DisconnectPacket::decode(&payload).ok_or(ProtocolError::Malformed { packet_id })?;
+                    self.shared.events.push(ServerEvent::ClientDisconnected { reason: disconnect.reason_text().to_string() });
+                    return Ok(None);
+                }
+                _ => return Ok(Some((packet_id, payload))),
+            }
+        }
+    }
+
+    /// Lê o próximo pacote, que precisa ser um `P`
+    async fn expect<P: Packet>(&mut self) -> Result<P, ProtocolError> {
+        let (packet_id, payload) = self.next_packet().await?.ok_or(ProtocolError::StreamClosed)?;
+
+        if packet_id != P::ID {
+            return Err(ProtocolError::IllegalPacket { packet_id, phase: self.phase });
+        }
+        P::decode(&payload).ok_or(ProtocolError::Malformed { packet_id })
+    }
+
+    /// Manda o Disconnect e espera o cliente fechar a conexão
+    async fn disconnect(&mut self, reason: &str) -> Result<(), ProtocolError> {
+        self.send(&DisconnectPacket::new(reason)).await?;
+        let _ = self.send.finish();
+
+        if tokio::time::timeout(DISCONNECT_GRACE, self.conn.closed()).await.is_err() {
+            self.conn.close(quinn::VarInt::from_u32(0), reason.as_bytes());
+        }
+        Ok(())
+    }
+
+    async fn end(&mut self, reason: &str) -> Result<Next, ProtocolError> {
+        self.disconnect(reason).await?;
+        Ok(Next::Ended)
+    }
+}
@@ -0,0 +1,428 @@
+//! Mock Hytale session service and OAuth token endpoint on localhost
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+use crate::auth::AuthEndpoints;
+use crate::utils::{random_uuid, unix_now};
+use super::jwt::MockSigner;
+
+/// Validade do refresh token OAuth emitido pelo mock
+const REFRESH_TOKEN_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// ============================================================================
+// Configuração
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct MockSessionConfig {
+    /// Nome do jogador no identity token
+    pub username: String,
+    /// Conta dona dos tokens OAuth (`sub` do access token)
+    pub account_id: Uuid,
+    /// Validade dos tokens de sessão, grants e access tokens
+    pub token_ttl: Duration,
+    /// `cnf.x5t#S256` dos access tokens no lugar do fingerprint pedido, para simular
+    /// um token preso a outro certificado
+    pub bound_fingerprint: Option<String>,
+}
+
+impl Default for MockSessionConfig {
+    fn default() -> Self {
+        Self {
+            username: "MockPlayer".to_string(),
+            account_id: random_uuid(),
+            token_ttl: Duration::from_secs(60 * 60),
+            bound_fingerprint: None,
+        }
+    }
+}
+
+// ============================================================================
+// Serviço
+// ============================================================================
+
+/// Session service falso, com os endpoints que o bot usa no login e na entrada em servidores
+///
+/// Qualquer código de autorização é aceito, então o login roda com `LoginMode::Stdin`
+/// ou `LoginMode::File` sem navegador. Os tokens são JWTs EdDSA de verdade, assinados
+/// pela chave do mock.
+pub struct MockSessionService {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    server: JoinHandle<()>,
+}
+
+impl MockSessionService {
+    pub async fn start() -> io::Result<Self> {
+        Self::start_with(MockSessionConfig::default()).await
+    }
+
+    /// Sobe o serviço numa porta livre de 127.0.0.1
+    pub async fn start_with(config: MockSessionConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(MockState {
+            issuer: format!("http://{}", addr),
+            config,
+            signer: MockSigner::generate(),
+            revoked: Mutex::new(HashSet::new()),
+        });
+
+        let server = tokio::spawn(warp::serve(routes(state.clone())).incoming(listener).run());
+
+        Ok(Self { addr, state, server })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.state.issuer
+    }
+
+    /// Endpoints para criar um `AuthClient` apontando para o mock
+    pub fn endpoints(&self) -> AuthEndpoints {
+        AuthEndpoints::with_base_url(self.base_url())
+    }
+
+    pub fn signer(&self) -> &MockSigner {
+        &self.state.signer
+    }
+
+    /// Emissão e validação de tokens para o servidor de jogo falso
+    pub fn authority(&self) -> MockAuthority {
+        MockAuthority { state: self.state.clone() }
+    }
+}
+
+impl Drop for MockSessionService {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+// ============================================================================
+// Autoridade
+// ============================================================================
+
+/// Faz do lado do servidor de jogo o que ele faria com o session service real
+///
+/// Continua válida depois que o `MockSessionService` é descartado, só sem HTTP.
+#[derive(Clone)]
+pub struct MockAuthority {
+    state: Arc<MockState>,
+}
+
+impl MockAuthority {
+    /// Identity token para um jogador ou servidor que não passou pelo login
+    pub fn issue_identity_token(&self, subject: Uuid, username: &str) -> String {
+        self.state.identity_token(&subject.to_string(), username)
+    }
+
+    /// Grant para o dono do identity token, a ser trocado por um access token de `audience`
+    pub fn issue_auth_grant(&self, identity_token: &str, audience: &str) -> Option<String> {
+        self.state.auth_grant(identity_token, audience)
+    }
+
+    /// Troca o grant por um access token preso ao certificado com este fingerprint
+    pub fn exchange_auth_grant(&self, grant: &str, x509_fingerprint: &str) -> Option<String> {
+        self.state.exchange_grant(grant, x509_fingerprint)
+    }
+
+    /// Claims do token, se ele foi emitido pelo mock para este uso e ainda vale
+    ///
+    /// `token_use` é `identity`, `session`, `grant` ou `server-access`.
+    pub fn verify(&self, token: &str, token_use: &str) -> Option<Value> {
+        self.state.verify(token, token_use)
+    }
+}
+
+impl std::fmt::Debug for MockAuthority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockAuthority").field("issuer", &self.state.issuer).finish()
+    }
+}
+
+// ============================================================================
+// Estado e emissão de tokens
+// ============================================================================
+
+struct MockState {
+    issuer: String,
+    config: MockSessionConfig,
+    signer: MockSigner,
+    /// Session tokens encerrados ou substituídos por um refresh
+    revoked: Mutex<HashSet<String>>,
+}
+
+impl MockState {
+    fn issue(&self, subject: &str, token_use: &str, ttl: Duration, extra: Value) -> String {
+        let now = unix_now();
+        let mut claims = json!({
+            "iss": self.issuer,
+            "sub": subject,
+            "iat": now,
+            "exp": now + ttl.as_secs(),
+            "jti": random_uuid().to_string(),
+            "token_use": token_use,
+        });
+        if let (Some(claims), Value::Object(extra)) = (claims.as_object_mut(), extra) {
+            claims.extend(extra);
+        }
+
+        self.signer.sign(&claims)
+    }
+
+    fn identity_token(&self, subject: &str, username: &str) -> String {
+        self.issue(subject, "identity", self.config.token_ttl, json!({
+            "scope": "hytale:client",
+            "username": username,
+        }))
+    }
+
+    fn session_tokens(&self, subject: &str) -> Value {
+        let session_token = self.issue(subject, "session", self.config.token_ttl, json!({
+            "scope": "hytale:client",
+            "sid": random_uuid().to_string(),
+        }));
+
+        json!({
+            "identityToken": self.identity_token(subject, &self.config.username),
+            "sessionToken": session_token,
+        })
+    }
+
+    fn oauth_tokens(&self) -> Value {
+        let subject = self.config.account_id.to_string();
+        let scope = "openid offline auth:launcher";
+
+        json!({
+            "access_token": self.issue(&subject, "access", self.config.token_ttl, json!({ "scope": scope })),
+            "refresh_token": self.issue(&subject, "refresh", REFRESH_TOKEN_TTL, json!({})),
+            "expires_in": self.config.token_ttl.as_secs(),
+            "scope": scope,
+            "token_type": "bearer",
+        })
+    }
+
+    fn auth_grant(&self, identity_token: &str, audience: &str) -> Option<String> {
+        let identity = self.verify(identity_token, "identity")?;
+        Some(self.issue(subject(&identity), "grant", self.config.token_ttl, json!({ "aud": audience })))
+    }
+
+    /// O access token fica preso ao certificado pelo claim cnf
+    fn exchange_grant(&self, grant: &str, x509_fingerprint: &str) -> Option<String> {
+        let grant = self.verify(grant, "grant")?;
+        let x509_fingerprint = self.config.bound_fingerprint.as_deref().unwrap_or(x509_fingerprint);
+        Some(self.issue(subject(&grant), "server-access", self.config.token_ttl, json!({
+            "aud": grant.get("aud").cloned().unwrap_or(Value::Null),
+            "scope": "hytale:client",
+            "cnf": { "x5t#S256": x509_fingerprint },
+        })))
+    }
+
+    /// Claims do token, se ele foi emitido por este mock para o uso esperado e ainda vale
+    fn verify(&self, token: &str, token_use: &str) -> Option<Value> {
+        if self.revoked.lock().unwrap().contains(token) {
+            return None;
+        }
+
+        self.signer.verify(token)
+            .filter(|claims| claims.get("token_use").and_then(Value::as_str) == Some(token_use))
+    }
+
+    /// Confere o `Authorization: Bearer ...`, retornando o token e seus claims
+    ///
+    /// O erro é a mensagem da resposta 401.
+    fn authorize(&self, header: Option<String>, token_use: &str) -> Result<(String, Value), &'static str> {
+        let token = header
+            .as_deref()
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or("missing bearer token")?;
+
+        let claims = self.verify(token, token_use).ok_or("invalid or expired token")?;
+
+        Ok((token.to_string(), claims))
+    }
+}
+
+// ============================================================================
+// Rotas
+// ============================================================================
+
+#[derive(Deserialize)]
+struct NewSessionRequest {
+    uuid: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthGrantRequest {
+    identity_token: String,
+    aud: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthTokenRequest {
+    authorization_grant: String,
+    x509_fingerprint: String,
+}
+
+fn routes(state: Arc<MockState>) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone + Send + Sync + 'static {
+    let with_state = warp::any().map(move || state.clone());
+    let bearer = warp::header::optional::<String>("authorization");
+
+    let oauth_token = warp::path!("oauth2" / "token")
+        .and(warp::post())
+        .and(with_state.clone())
+        .and(warp::body::form::<HashMap<String, String>>())
+        .map(|state: Arc<MockState>, form| oauth_token(&state, form));
+
+    let new_session = warp::path!("game-session" / "new")
+        .and(warp::post())
+        .and(with_state.clone())
+        .and(bearer)
+        .and(warp::body::json::<NewSessionRequest>())
+        .map(|state: Arc<MockState>, auth, body| new_session(&state, auth, body));
+
+    let refresh_session = warp::path!("game-session" / "refresh")
+        .and(warp::post())
+        .and(with_state.clone())
+        .and(bearer)
+        .map(|state: Arc<MockState>, auth| refresh_session(&state, auth));
+
+    let delete_session = warp::path!("game-session")
+        .and(warp::delete())
+        .and(with_state.clone())
+        .and(bearer)
+        .map(|state: Arc<MockState>, auth| delete_session(&state, auth));
+
+    let auth_grant = warp::path!("server-join" / "auth-grant")
+        .and(warp::post())
+        .and(with_state.clone())
+        .and(bearer)
+        .and(warp::body::json::<AuthGrantRequest>())
+        .map(|state: Arc<MockState>, auth, body| auth_grant(&state, auth, body));
+
+    let auth_token = warp::path!("server-join" / "auth-token")
+        .and(warp::post())
+        .and(with_state.clone())
+        .and(bearer)
+        .and(warp::body::json::<AuthTokenRequest>())
+        .map(|state: Arc<MockState>, auth, body| auth_token(&state, auth, body));
+
+    let jwks = warp::path!(".well-known" / "jwks.json")
+        .and(warp::get())
+        .and(with_state)
+        .map(|state: Arc<MockState>| warp::reply::json(&state.signer.jwks()).into_response());
+
+    oauth_token
+        .or(new_session).unify()
+        .or(refresh_session).unify()
+        .or(delete_session).unify()
+        .or(auth_grant).unify()
+        .or(auth_token).unify()
+        .or(jwks).unify()
+}
+
+fn oauth_token(state: &MockState, form: HashMap<String, String>) -> Response {
+    let field = |name: &str| form.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+    match field("grant_type") {
+        // Qualquer código serve, desde que o cliente mande o verifier do PKCE
+        Some("authorization_code") if field("code").is_some() && field("code_verifier").is_some() => {
+            json_reply(state.oauth_tokens())
+        }
+        Some("authorization_code") => error_reply(StatusCode::BAD_REQUEST, "invalid_grant"),
+
+        Some("refresh_token") => match field("refresh_token").and_then(|t| state.verify(t, "refresh")) {
+            Some(_) => json_reply(state.oauth_tokens()),
+            None => error_reply(StatusCode::BAD_REQUEST, "invalid_grant"),
+        },
+
+        _ => error_reply(StatusCode::BAD_REQUEST, "unsupported_grant_type"),
+    }
+}
+
+fn new_session(state: &MockState, auth: Option<String>, body: NewSessionRequest) -> Response {
+    if let Err(message) = state.authorize(auth, "access") {
+        return error_reply(StatusCode::UNAUTHORIZED, message);
+    }
+    if Uuid::parse_str(&body.uuid).is_err() {
+        return error_reply(StatusCode::BAD_REQUEST, "invalid uuid");
+    }
+    json_reply(state.session_tokens(&body.uuid))
+}
+
+fn refresh_session(state: &MockState, auth: Option<String>) -> Response {
+    let (token, claims) = match state.authorize(auth, "session") {
+        Ok(authorized) => authorized,
+        Err(message) => return error_reply(StatusCode::UNAUTHORIZED, message),
+    };
+
+    // O session token antigo deixa de valer, como no serviço real
+    state.revoked.lock().unwrap().insert(token);
+    json_reply(state.session_tokens(subject(&claims)))
+}
+
+fn delete_session(state: &MockState, auth: Option<String>) -> Response {
+    match state.authorize(auth, "session") {
+        Ok((token, _)) => {
+            state.revoked.lock().unwrap().insert(token);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(message) => error_reply(StatusCode::UNAUTHORIZED, message),
+    }
+}
+
+fn auth_grant(state: &MockState, auth: Option<String>, body: AuthGrantRequest) -> Response {
+    if let Err(message) = state.authorize(auth, "session") {
+        return error_reply(StatusCode::UNAUTHORIZED, message);
+    }
+
+    match state.auth_grant(&body.identity_token, &body.aud) {
+        Some(grant) => json_reply(json!({ "authorizationGrant": grant })),
+        None => error_reply(StatusCode::UNAUTHORIZED, "invalid identity token"),
+    }
+}
+
+fn auth_token(state: &MockState, auth: Option<String>, body: AuthTokenRequest) -> Response {
+    if let Err(message) = state.authorize(auth, "session") {
+        return error_reply(StatusCode::UNAUTHORIZED, message);
+    }
+
+    match state.exchange_grant(&body.authorization_grant, &body.x509_fingerprint) {
+        Some(access_token) => json_reply(json!({ "accessToken": access_token })),
+        None => error_reply(StatusCode::UNAUTHORIZED, "invalid authorization grant"),
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn subject(claims: &Value) -> &str {
+    claims.get("sub").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn json_reply(body: Value) -> Response {
+    warp::reply::json(&body).into_response()
+}
+
+fn error_reply(status: StatusCode, message: &str) -> Response {
+    warp::reply::with_status(warp::reply::json(&json!({ "error": message })), status).into_response()
+}
@@ -1,111 +1,317 @@
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
-use quinn::{ClientConfig, TransportConfig};
+use quinn::congestion::{BbrConfig, CubicConfig, NewRenoConfig};
+use quinn::{ClientConfig, Endpoint, IdleTimeout, MtuDiscoveryConfig, TransportConfig, VarInt};
 use rustls::RootCertStore;
-use sha2::{Sha256, Digest};
-use base64::{engine::general_purpose, Engine as _};
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+use crate::error::ConnectError;
+use crate::identity::ClientIdentity;
+use crate::trust::{ServerTrust, TrustVerifier};
 
 const MAX_UDP_PAYLOAD_SIZE: u16 = 1200;
 
-#[derive(Debug)]
-struct SkipServerVerification;
+/// Porta padrão dos servidores Hytale
+pub const DEFAULT_PORT: u16 = 5520;
 
-impl SkipServerVerification {
-    fn new() -> Arc<Self> {
-        Arc::new(Self)
+/// Quanto esperar uma tentativa antes de começar a do próximo endereço
+const HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+// ============================================================================
+// Endereço
+// ============================================================================
+
+/// Servidor no formato `host[:port]`, com `DEFAULT_PORT` se a porta for omitida
+///
+/// IPv6 com porta vai entre colchetes (`[::1]:5520`); sem porta pode ir sem eles.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServerAddress {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self { host: host.into(), port }
+    }
+
+    /// Endereços do host no DNS, alternando as famílias a partir da primeira resposta
+    pub async fn resolve(&self) -> Result<Vec<SocketAddr>, ConnectError> {
+        let addrs = tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .map_err(|source| ConnectError::Resolve { address: self.to_string(), source })?;
+
+        let mut addrs: Vec<SocketAddr> = addrs.collect();
+        addrs.dedup();
+        if addrs.is_empty() {
+            return Err(ConnectError::Unresolved(self.to_string()));
+        }
+        Ok(interleave_families(addrs))
     }
 }
 
-impl rustls::client::danger::ServerCertVerifier for SkipServerVerification {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &rustls::pki_types::CertificateDer<'_>,
-        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
-        _server_name: &rustls::pki_types::ServerName<'_>,
-        _ocsp_response: &[u8],
-        _now: rustls::pki_types::UnixTime,
-    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::danger::ServerCertVerified::assertion())
+impl FromStr for ServerAddress {
+    type Err = ConnectError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConnectError::InvalidAddress(address.to_string());
+        let parse_port = |port: &str| port.parse::<u16>().map_err(|_| invalid());
+
+        let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+            let (host, after) = rest.split_once(']').ok_or_else(invalid)?;
+            match after {
+                "" => (host, DEFAULT_PORT),
+                _ => (host, parse_port(after.strip_prefix(':').ok_or_else(invalid)?)?),
+            }
+        } else if address.parse::<Ipv6Addr>().is_ok() {
+            (address, DEFAULT_PORT)
+        } else {
+            match address.rsplit_once(':') {
+                Some((host, port)) => (host, parse_port(port)?),
+                None => (address, DEFAULT_PORT),
+            }
+        };
+
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        Ok(Self::new(host, port))
     }
+}
 
-    fn verify_tls12_signature(
-        &self,
-        _message: &[u8],
-        _cert: &rustls::pki_types::CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
     }
+}
 
-    fn verify_tls13_signature(
-        &self,
-        _message: &[u8],
-        _cert: &rustls::pki_types::CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+impl From<SocketAddr> for ServerAddress {
+    fn from(addr: SocketAddr) -> Self {
+        Self::new(addr.ip().to_string(), addr.port())
     }
+}
+
+/// Alterna IPv6 e IPv4 mantendo a ordem do resolver dentro de cada família
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_v6 = addrs[0].is_ipv6();
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|addr| addr.is_ipv6() == prefer_v6);
 
-    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
-        vec![
-            rustls::SignatureScheme::RSA_PKCS1_SHA1,
-            rustls::SignatureScheme::ECDSA_SHA1_Legacy,
-            rustls::SignatureScheme::RSA_PKCS1_SHA256,
-            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
-            rustls::SignatureScheme::RSA_PKCS1_SHA384,
-            rustls::SignatureScheme::ECDSA_NISTP384_SHA384,
-            rustls::SignatureScheme::RSA_PKCS1_SHA512,
-            rustls::SignatureScheme::ECDSA_NISTP521_SHA512,
-            rustls::SignatureScheme::RSA_PSS_SHA256,
-            rustls::SignatureScheme::RSA_PSS_SHA384,
-            rustls::SignatureScheme::RSA_PSS_SHA512,
-            rustls::SignatureScheme::ED25519,
-            rustls::SignatureScheme::ED448,
-        ]
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut interleaved = Vec::new();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return interleaved,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
     }
 }
 
-pub fn configure_client() -> (ClientConfig, String) {
-    let _ = rustls::crypto::ring::default_provider().install_default();
+// ============================================================================
+// Transporte
+// ============================================================================
 
-    let root_store = RootCertStore::empty();
+/// Algoritmo de controle de congestionamento do QUIC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CongestionController {
+    #[default]
+    Cubic,
+    #[value(name = "newreno")]
+    NewReno,
+    /// Melhor em links com perda ou latência alta, ainda experimental no quinn
+    Bbr,
+}
+
+/// Parâmetros do transporte QUIC, para ajustar o bot a links lentos ou downloads grandes
+///
+/// Janelas em `None` ficam com o padrão do quinn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportOptions {
+    /// Sem nenhum pacote por este tempo a conexão é dada como morta
+    pub idle_timeout: Duration,
+    /// Intervalo dos keep-alives, precisa ser menor que o `idle_timeout`
+    pub keep_alive_interval: Option<Duration>,
+    /// Tamanho inicial dos datagramas UDP (1200 passa em qualquer rede)
+    pub initial_mtu: u16,
+    /// Sobe o MTU aos poucos se o caminho aguentar
+    pub mtu_discovery: bool,
+    /// Bytes que o servidor pode mandar num stream sem esperar confirmação
+    pub stream_receive_window: Option<u64>,
+    /// Bytes que o servidor pode mandar na conexão toda sem esperar confirmação
+    pub receive_window: Option<u64>,
+    /// Bytes enviados e ainda não confirmados pelo servidor
+    pub send_window: Option<u64>,
+    pub congestion_controller: CongestionController,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(30),
+            keep_alive_interval: Some(Duration::from_secs(5)),
+            initial_mtu: MAX_UDP_PAYLOAD_SIZE,
+            mtu_discovery: true,
+            stream_receive_window: None,
+            receive_window: None,
+            send_window: None,
+            congestion_controller: CongestionController::default(),
+        }
+    }
+}
+
+impl TransportOptions {
+    fn transport_config(&self) -> Result<TransportConfig, ConnectError> {
+        let mut config = TransportConfig::default();
 
-    // Gerar certificado self-signed para mTLS (requerido pelo servidor Hytale)
-    let subject_alt_names = vec!["hytale_client".to_string()];
-    let certified_key = rcgen::generate_simple_self_signed(subject_alt_names).expect("Failed to generate client cert");
+        let idle_timeout = IdleTimeout::try_from(self.idle_timeout)
+            .map_err(|_| ConnectError::InvalidTransport("idle_timeout"))?;
+        // Keep-alive mais espaçado que o idle timeout não segura a conexão aberta
+        if self.keep_alive_interval.is_some_and(|interval| interval >= self.idle_timeout) {
+            return Err(ConnectError::InvalidTransport("keep_alive_interval"));
+        }
+        config.max_idle_timeout(Some(idle_timeout));
+        config.keep_alive_interval(self.keep_alive_interval);
 
-    let cert_der = certified_key.cert.der().clone();
-    let priv_key = certified_key.signing_key.serialize_der();
+        config.initial_mtu(self.initial_mtu);
+        config.mtu_discovery_config(self.mtu_discovery.then(MtuDiscoveryConfig::default));
 
-    let mut hasher = Sha256::new();
-    hasher.update(&cert_der.as_ref());
-    let fingerprint = hasher.finalize();
-    let x509_fingerprint = general_purpose::URL_SAFE_NO_PAD.encode(&fingerprint);
+        if let Some(window) = self.stream_receive_window {
+            config.stream_receive_window(var_int(window, "stream_receive_window")?);
+        }
+        if let Some(window) = self.receive_window {
+            config.receive_window(var_int(window, "receive_window")?);
+        }
+        if let Some(window) = self.send_window {
+            config.send_window(window);
+        }
 
-    let cert_chain = vec![cert_der];
-    let key_der = rustls::pki_types::PrivateKeyDer::Pkcs8(priv_key.into());
+        match self.congestion_controller {
+            CongestionController::Cubic => config.congestion_controller_factory(Arc::new(CubicConfig::default())),
+            CongestionController::NewReno => config.congestion_controller_factory(Arc::new(NewRenoConfig::default())),
+            CongestionController::Bbr => config.congestion_controller_factory(Arc::new(BbrConfig::default())),
+        };
+
+        Ok(config)
+    }
+}
+
+fn var_int(value: u64, option: &'static str) -> Result<VarInt, ConnectError> {
+    VarInt::from_u64(value).map_err(|_| ConnectError::InvalidTransport(option))
+}
+
+// ============================================================================
+// Cliente
+// ============================================================================
+
+/// Config QUIC/TLS do cliente, apresentando `identity` no mTLS (requerido pelo servidor Hytale)
+///
+/// O certificado de `server` é checado segundo `trust`; a config só serve para esse servidor.
+pub fn configure_client(
+    identity: &ClientIdentity,
+    server: &ServerAddress,
+    trust: ServerTrust,
+    transport: &TransportOptions,
+) -> Result<ClientConfig, ConnectError> {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let root_store = RootCertStore::empty();
 
     let mut tls_config = rustls::ClientConfig::builder()
         .with_root_certificates(root_store)
-        .with_client_auth_cert(cert_chain, key_der)
-        .expect("Failed to configure client auth");
+        .with_client_auth_cert(identity.cert_chain(), identity.private_key())?;
 
-    tls_config.dangerous().set_certificate_verifier(SkipServerVerification::new());
+    tls_config.dangerous().set_certificate_verifier(TrustVerifier::new(&server.to_string(), trust, provider));
 
     tls_config.alpn_protocols = vec![
         b"hytale/1".to_vec(),
     ];
 
-    let mut transport_config = TransportConfig::default();
-    transport_config.max_idle_timeout(Some(Duration::from_secs(30).try_into().unwrap()));
-    transport_config.keep_alive_interval(Some(Duration::from_secs(5)));
-    transport_config.initial_mtu(MAX_UDP_PAYLOAD_SIZE);
-
-    let quic_crypto = quinn::crypto::rustls::QuicClientConfig::try_from(tls_config)
-        .expect("Failed to create QUIC client config");
+    let quic_crypto = quinn::crypto::rustls::QuicClientConfig::try_from(tls_config)?;
 
     let mut client_config = ClientConfig::new(Arc::new(quic_crypto));
-    client_config.transport_config(Arc::new(transport_config));
+    client_config.transport_config(Arc::new(transport.transport_config()?));
+
+    Ok(client_config)
+}
 
-    (client_config, x509_fingerprint)
+/// Abre a conexão QUIC com o servidor de jogo
+///
+/// Tenta os endereços resolvidos alternando IPv6 e IPv4, começando o próximo se o atual
+/// não responder em `HAPPY_EYEBALLS_DELAY` (RFC 8305). Cada família usa um endpoint
+/// próprio; o do endereço que conectou volta junto para o `wait_idle` no fim.
+pub async fn connect(config: &ClientConfig, address: &ServerAddress) -> Result<(Endpoint, quinn::Connection), ConnectError> {
+    let mut candidates = address.resolve().await?.into_iter().peekable();
+    let mut endpoints = FamilyEndpoints::default();
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(addr) = candidates.next() {
+            // Família sem rota (ex: sem IPv6 na máquina) falha na hora: segue para o próximo
+            let started = endpoints.for_target(addr).and_then(|endpoint| {
+                let connecting = endpoint.connect_with(config.clone(), addr, &address.host)?;
+                Ok((endpoint, connecting))
+            });
+            match started {
+                Ok((endpoint, connecting)) => {
+                    attempts.spawn(async move { connecting.await.map(|connection| (endpoint, connection)) });
+                }
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            }
+        }
+
+        // Com endereços sobrando, a tentativa atual só tem um tempo de vantagem
+        let finished = if candidates.peek().is_some() {
+            match timeout(HAPPY_EYEBALLS_DELAY, attempts.join_next()).await {
+                Ok(finished) => finished,
+                Err(_) => continue,
+            }
+        } else {
+            attempts.join_next().await
+        };
+
+        match finished {
+            // As outras tentativas são abortadas quando o JoinSet sai de escopo
+            Some(Ok(Ok(connected))) => return Ok(connected),
+            Some(Ok(Err(e))) => last_error = Some(e.into()),
+            Some(Err(e)) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // Tentativa cancelada conta como endereço que falhou
+            Some(Err(_)) => {}
+            None => break,
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| ConnectError::Unresolved(address.to_string())))
+}
+
+/// Um endpoint por família, criado só se algum endereço dela for tentado
+#[derive(Default)]
+struct FamilyEndpoints {
+    v4: Option<Endpoint>,
+    v6: Option<Endpoint>,
+}
+
+impl FamilyEndpoints {
+    fn for_target(&mut self, target: SocketAddr) -> Result<Endpoint, ConnectError> {
+        let (slot, bind) = match target {
+            SocketAddr::V4(_) => (&mut self.v4, SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+            SocketAddr::V6(_) => (&mut self.v6, SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))),
+        };
+
+        if let Some(endpoint) = slot {
+            return Ok(endpoint.clone());
+        }
+        Ok(slot.insert(Endpoint::client(bind)?).clone())
+    }
 }
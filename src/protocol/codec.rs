@@ -1,35 +1,32 @@
 //! Protocol encoding/decoding utilities
 
-use std::error::Error;
 use std::time::Duration;
-use quinn::RecvStream;
-use tokio::time::timeout;
-
-type BoxError = Box<dyn Error + Send + Sync>;
+use quinn::{ReadExactError, RecvStream};
+use super::error::ProtocolError;
+use super::versions::ProtocolVersion;
 
 // ============================================================================
 // VarInt
 // ============================================================================
 
 /// Lê um VarInt do buffer, retorna (valor, bytes_consumidos)
-pub fn read_varint(data: &[u8]) -> (u32, usize) {
+///
+/// None se o buffer acabar antes do último byte ou se o VarInt passar de 32 bits.
+pub fn read_varint(data: &[u8]) -> Option<(u32, usize)> {
     let mut value: u32 = 0;
-    let mut shift = 0;
-    let mut bytes_read = 0;
 
-    for &byte in data {
-        bytes_read += 1;
-        value |= ((byte & 0x7F) as u32) << shift;
-        if byte & 0x80 == 0 {
-            break;
+    for (i, &byte) in data.iter().take(5).enumerate() {
+        // O quinto byte só tem espaço para os 4 bits mais altos
+        if i == 4 && byte > 0x0F {
+            return None;
         }
-        shift += 7;
-        if shift >= 35 {
-            break;
+        value |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
         }
     }
 
-    (value, bytes_read)
+    None
 }
 
 /// Escreve um VarInt no buffer
@@ -60,61 +57,640 @@ pub fn varint_size(value: u32) -> usize {
 // Packet Reading
 // ============================================================================
 
-/// Lê um pacote do stream com timeout
-pub async fn read_packet(recv: &mut RecvStream) -> Result<(u32, Vec<u8>), BoxError> {
-    let mut header = [0u8; 8];
+/// Timeout padrão de leitura durante o handshake
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
 
-    match timeout(Duration::from_secs(10), recv.read_exact(&mut header)).await {
-        Ok(Ok(())) => {},
-        Ok(Err(e)) => return Err(format!("Erro lendo header: {}", e).into()),
-        Err(_) => return Err("Timeout lendo header".into()),
-    }
+/// Maior payload aceito no fio, checado antes de alocar o buffer
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Maior payload aceito depois de descomprimir
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Nível zstd usado nos pacotes que o bot comprime
+const ZSTD_LEVEL: i32 = 3;
+
+/// Lê um pacote do stream, descomprimindo o payload se a versão marca o ID como comprimido
+///
+/// Não tem timeout próprio: quem chama decide quanto esperar (ver `Phase`).
+/// O ID retornado é o do fio, sem conversão para a versão atual.
+pub async fn read_packet(recv: &mut RecvStream, version: &ProtocolVersion) -> Result<(u32, Vec<u8>), ProtocolError> {
+    let mut header = [0u8; 8];
+    recv.read_exact(&mut header).await?;
 
     let payload_len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
     let packet_id = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
 
+    if payload_len > MAX_FRAME_SIZE {
+        return Err(ProtocolError::FrameTooLarge { packet_id, size: payload_len, max: MAX_FRAME_SIZE });
+    }
+
     let mut payload = vec![0u8; payload_len];
     if payload_len > 0 {
-        match timeout(Duration::from_secs(10), recv.read_exact(&mut payload)).await {
-            Ok(Ok(())) => {},
-            Ok(Err(e)) => return Err(format!("Erro lendo payload: {}", e).into()),
-            Err(_) => return Err("Timeout lendo payload".into()),
-        }
+        recv.read_exact(&mut payload).await.map_err(|e| match e {
+            ReadExactError::FinishedEarly(got) => ProtocolError::Truncated { packet_id, needed: payload_len, got },
+            e => e.into(),
+        })?;
+    }
+
+    // Pacotes grandes (WorldSettings, assets) chegam comprimidos com zstd
+    if version.is_compressed(packet_id) {
+        payload = zstd::bulk::decompress(&payload, MAX_DECOMPRESSED_SIZE)
+            .map_err(|source| ProtocolError::Decompress { packet_id, source })?;
     }
 
     Ok((packet_id, payload))
 }
 
+/// Comprime um payload para os pacotes marcados como `compressed` no protocolo
+pub fn compress_payload(payload: &[u8]) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::compress(payload, ZSTD_LEVEL)
+}
+
+// ============================================================================
+// Packet Writing
+// ============================================================================
+
+/// Monta o frame [Payload Length (u32 LE)] [Packet ID (u32 LE)] [Payload]
+pub fn frame_packet(packet_id: u32, payload: Vec<u8>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&packet_id.to_le_bytes());
+    frame.extend(payload);
+    frame
+}
+
 // ============================================================================
 // VarString Helpers
 // ============================================================================
 
-pub fn read_varstring_at(data: &[u8], var_block_start: usize, offset: i32) -> Option<String> {
-    if offset < 0 { return None; }
+/// Escreve um array de bytes com prefixo VarInt
+pub fn write_varbytes(buf: &mut Vec<u8>, value: &[u8]) {
+    write_varint(buf, value.len() as u32);
+    buf.extend_from_slice(value);
+}
+
+/// Tamanho serializado de uma VarString/VarBytes
+pub fn varbytes_size(len: usize) -> usize {
+    varint_size(len as u32) + len
+}
+
+pub fn read_i32_le(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Escreve uma string ASCII num campo de tamanho fixo, completando com zeros
+pub fn write_fixed_ascii(buf: &mut Vec<u8>, value: &str, size: usize) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(size);
+    buf.extend_from_slice(&bytes[..len]);
+    buf.resize(buf.len() + size - len, 0);
+}
+
+/// Lê uma string ASCII de tamanho fixo, descartando o preenchimento com zeros
+pub fn read_fixed_ascii(data: &[u8], offset: usize, size: usize) -> Option<String> {
+    let bytes = data.get(offset..offset + size)?;
+    let s = std::str::from_utf8(bytes).ok()?;
+    Some(s.trim_end_matches('\0').to_string())
+}
+
+// ============================================================================
+// Layout (nullBits + fixed block + offsets + variable block)
+// ============================================================================
+//
+// Todo pacote/estrutura Hytale segue o mesmo formato:
+//
+//   [nullBits] [fixed block] [offsets i32 LE, -1 = null] [variable block]
+//
+// - nullBits: um bit por campo anulável, na ordem de declaração
+// - offsets: só existem com 2+ campos variáveis; com um só, ele é escrito
+//   direto no início do variable block
+// - campos fixos anuláveis ocupam o espaço mesmo quando null (zerados)
+
+/// Tipo serializado com tamanho fixo dentro do fixed block
+pub trait FixedCodec: Sized {
+    const SIZE: usize;
+
+    fn write_fixed(&self, buf: &mut Vec<u8>);
 
-    let pos = var_block_start + offset as usize;
-    if pos >= data.len() { return None; }
+    /// Recebe exatamente `SIZE` bytes
+    fn read_fixed(bytes: &[u8]) -> Option<Self>;
+}
 
-    let (len, sz) = read_varint(&data[pos..]);
-    let end = pos + sz + len as usize;
-    if end > data.len() { return None; }
+/// Tipo serializado no variable block
+pub trait VarCodec: Sized {
+    fn write_var(&self, buf: &mut Vec<u8>);
 
-    String::from_utf8(data[pos + sz..end].to_vec()).ok()
+    /// Recebe o resto do buffer, retorna (valor, bytes consumidos)
+    fn read_var(data: &[u8]) -> Option<(Self, usize)>;
 }
 
-pub fn read_varbytes_at(data: &[u8], var_block_start: usize, offset: i32) -> Option<Vec<u8>> {
-    if offset < 0 { return None; }
+macro_rules! impl_fixed_codec_le {
+    ($($ty:ty),*) => {$(
+        impl FixedCodec for $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+
+            fn write_fixed(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_le_bytes());
+            }
 
-    let pos = var_block_start + offset as usize;
-    if pos >= data.len() { return None; }
+            fn read_fixed(bytes: &[u8]) -> Option<Self> {
+                Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
 
-    let (len, sz) = read_varint(&data[pos..]);
-    let end = pos + sz + len as usize;
-    if end > data.len() { return None; }
+impl_fixed_codec_le!(u8, u16, i16, i32, u32, i64, f32, f64);
+
+impl FixedCodec for bool {
+    const SIZE: usize = 1;
+
+    fn write_fixed(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
 
-    Some(data[pos + sz..end].to_vec())
+    fn read_fixed(bytes: &[u8]) -> Option<Self> {
+        Some(*bytes.first()? != 0)
+    }
 }
 
-pub fn read_i32_le(data: &[u8], offset: usize) -> i32 {
-    i32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+impl FixedCodec for uuid::Uuid {
+    const SIZE: usize = 16;
+
+    /// UUID como 16 bytes (dois i64 Big Endian)
+    fn write_fixed(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn read_fixed(bytes: &[u8]) -> Option<Self> {
+        Some(uuid::Uuid::from_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl VarCodec for String {
+    fn write_var(&self, buf: &mut Vec<u8>) {
+        write_varbytes(buf, self.as_bytes());
+    }
+
+    fn read_var(data: &[u8]) -> Option<(Self, usize)> {
+        let (bytes, consumed) = Vec::<u8>::read_var(data)?;
+        Some((String::from_utf8(bytes).ok()?, consumed))
+    }
+}
+
+impl VarCodec for Vec<u8> {
+    fn write_var(&self, buf: &mut Vec<u8>) {
+        write_varbytes(buf, self);
+    }
+
+    fn read_var(data: &[u8]) -> Option<(Self, usize)> {
+        let (len, sz) = read_varint(data)?;
+        let end = sz.checked_add(len as usize)?;
+        Some((data.get(sz..end)?.to_vec(), end))
+    }
+}
+
+/// Arrays: VarInt count + elementos serializados em sequência
+impl<T: VarCodec> VarCodec for Vec<T> {
+    fn write_var(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.len() as u32);
+        for item in self {
+            item.write_var(buf);
+        }
+    }
+
+    fn read_var(data: &[u8]) -> Option<(Self, usize)> {
+        let (count, mut pos) = read_varint(data)?;
+        // Cada elemento ocupa pelo menos um byte: um count maior que o resto do buffer é lixo
+        let mut items = Vec::with_capacity((count as usize).min(data.len() - pos));
+        for _ in 0..count {
+            if pos >= data.len() {
+                return None;
+            }
+            let (item, consumed) = T::read_var(&data[pos..])?;
+            items.push(item);
+            pos += consumed;
+        }
+        Some((items, pos))
+    }
+}
+
+/// Escreve campos no layout Hytale, calculando nullBits e offsets
+#[derive(Default)]
+pub struct LayoutWriter {
+    null_bits: Vec<bool>,
+    fixed: Vec<u8>,
+    var_fields: Vec<Option<Vec<u8>>>,
+}
+
+impl LayoutWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn null_bit(&mut self, present: bool) {
+        self.null_bits.push(present);
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos fixos
+    // ------------------------------------------------------------------------
+
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.fixed.push(value);
+        self
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.u8(value as u8)
+    }
+
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.fixed.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i32(&mut self, value: i32) -> &mut Self {
+        self.fixed.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.fixed.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i64(&mut self, value: i64) -> &mut Self {
+        self.fixed.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// UUID como 16 bytes (dois i64 Big Endian)
+    pub fn uuid(&mut self, value: &uuid::Uuid) -> &mut Self {
+        self.fixed.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub fn fixed_ascii(&mut self, value: &str, size: usize) -> &mut Self {
+        write_fixed_ascii(&mut self.fixed, value, size);
+        self
+    }
+
+    /// Campo fixo anulável: ocupa `size` bytes zerados quando None
+    pub fn nullable_fixed<T>(
+        &mut self,
+        value: Option<&T>,
+        size: usize,
+        write: impl FnOnce(&mut Vec<u8>, &T),
+    ) -> &mut Self {
+        self.null_bit(value.is_some());
+        let start = self.fixed.len();
+        if let Some(value) = value {
+            write(&mut self.fixed, value);
+        }
+        self.fixed.resize(start + size, 0);
+        self
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos variáveis
+    // ------------------------------------------------------------------------
+
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.bytes(value.as_bytes())
+    }
+
+    pub fn opt_string(&mut self, value: Option<&str>) -> &mut Self {
+        self.opt_bytes(value.map(str::as_bytes))
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        let mut buf = Vec::with_capacity(varbytes_size(value.len()));
+        write_varbytes(&mut buf, value);
+        self.var_fields.push(Some(buf));
+        self
+    }
+
+    pub fn opt_bytes(&mut self, value: Option<&[u8]>) -> &mut Self {
+        self.null_bit(value.is_some());
+        match value {
+            Some(value) => self.bytes(value),
+            None => {
+                self.var_fields.push(None);
+                self
+            }
+        }
+    }
+
+    /// Campo variável já serializado (estruturas aninhadas, arrays)
+    pub fn raw(&mut self, value: Vec<u8>) -> &mut Self {
+        self.var_fields.push(Some(value));
+        self
+    }
+
+    pub fn opt_raw(&mut self, value: Option<Vec<u8>>) -> &mut Self {
+        self.null_bit(value.is_some());
+        self.var_fields.push(value);
+        self
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos genéricos (usados pelo derive HytalePacket)
+    // ------------------------------------------------------------------------
+
+    pub fn fixed<T: FixedCodec>(&mut self, value: &T) -> &mut Self {
+        value.write_fixed(&mut self.fixed);
+        self
+    }
+
+    pub fn opt_fixed<T: FixedCodec>(&mut self, value: Option<&T>) -> &mut Self {
+        self.nullable_fixed(value, T::SIZE, |buf, value| value.write_fixed(buf))
+    }
+
+    pub fn var<T: VarCodec>(&mut self, value: &T) -> &mut Self {
+        let mut buf = Vec::new();
+        value.write_var(&mut buf);
+        self.raw(buf)
+    }
+
+    pub fn opt_var<T: VarCodec>(&mut self, value: Option<&T>) -> &mut Self {
+        self.opt_raw(value.map(|value| {
+            let mut buf = Vec::new();
+            value.write_var(&mut buf);
+            buf
+        }))
+    }
+
+    // ------------------------------------------------------------------------
+    // Finalização
+    // ------------------------------------------------------------------------
+
+    /// Escreve nullBits, fixed block, offsets e variable block no buffer
+    pub fn finish(&self, buf: &mut Vec<u8>) {
+        for chunk in self.null_bits.chunks(8) {
+            let byte = chunk.iter().enumerate()
+                .fold(0u8, |acc, (bit, &present)| if present { acc | (1 << bit) } else { acc });
+            buf.push(byte);
+        }
+
+        buf.extend_from_slice(&self.fixed);
+
+        if let [single] = self.var_fields.as_slice() {
+            if let Some(bytes) = single {
+                buf.extend_from_slice(bytes);
+            }
+            return;
+        }
+
+        let mut current_offset: i32 = 0;
+        for field in &self.var_fields {
+            match field {
+                Some(bytes) => {
+                    buf.extend_from_slice(&current_offset.to_le_bytes());
+                    current_offset += bytes.len() as i32;
+                }
+                None => buf.extend_from_slice(&(-1i32).to_le_bytes()),
+            }
+        }
+        for bytes in self.var_fields.iter().flatten() {
+            buf.extend_from_slice(bytes);
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.finish(&mut buf);
+        buf
+    }
+}
+
+/// Lê campos do layout Hytale na mesma ordem em que foram escritos
+///
+/// Os campos fixos devem ser lidos antes dos variáveis. Os métodos retornam
+/// None quando o payload está malformado; campos anuláveis retornam
+/// `Some(None)` quando o null bit está desligado.
+pub struct LayoutReader<'a> {
+    data: &'a [u8],
+    null_bits_size: usize,
+    var_fields: usize,
+    next_null_bit: usize,
+    fixed_pos: usize,
+    next_var: usize,
+    end: usize,
+}
+
+impl<'a> LayoutReader<'a> {
+    /// `nullable_fields` e `var_fields` definem o tamanho do nullBits e da tabela de offsets
+    pub fn new(data: &'a [u8], nullable_fields: usize, var_fields: usize) -> Self {
+        let null_bits_size = nullable_fields.div_ceil(8);
+        Self {
+            data,
+            null_bits_size,
+            var_fields,
+            next_null_bit: 0,
+            fixed_pos: null_bits_size,
+            next_var: 0,
+            end: null_bits_size,
+        }
+    }
+
+    /// Quantos bytes do buffer o layout ocupou até agora
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    fn null_bit(&mut self) -> Option<bool> {
+        let bit = self.next_null_bit;
+        self.next_null_bit += 1;
+        if bit / 8 >= self.null_bits_size {
+            return None;
+        }
+        Some(self.data.get(bit / 8)? & (1 << (bit % 8)) != 0)
+    }
+
+    fn take_fixed(&mut self, size: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.fixed_pos..self.fixed_pos + size)?;
+        self.fixed_pos += size;
+        self.end = self.end.max(self.fixed_pos);
+        Some(bytes)
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos fixos
+    // ------------------------------------------------------------------------
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take_fixed(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Option<bool> {
+        Some(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take_fixed(2)?.try_into().ok()?))
+    }
+
+    pub fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_le_bytes(self.take_fixed(4)?.try_into().ok()?))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take_fixed(4)?.try_into().ok()?))
+    }
+
+    pub fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_le_bytes(self.take_fixed(8)?.try_into().ok()?))
+    }
+
+    pub fn uuid(&mut self) -> Option<uuid::Uuid> {
+        Some(uuid::Uuid::from_bytes(self.take_fixed(16)?.try_into().ok()?))
+    }
+
+    pub fn fixed_ascii(&mut self, size: usize) -> Option<String> {
+        let start = self.fixed_pos;
+        self.take_fixed(size)?;
+        read_fixed_ascii(self.data, start, size)
+    }
+
+    pub fn nullable_fixed<T>(&mut self, size: usize, read: impl FnOnce(&[u8]) -> Option<T>) -> Option<Option<T>> {
+        let present = self.null_bit()?;
+        let bytes = self.take_fixed(size)?;
+        if present { read(bytes).map(Some) } else { Some(None) }
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos variáveis
+    // ------------------------------------------------------------------------
+
+    /// Posição absoluta do próximo campo variável (None se estiver null)
+    fn next_var_pos(&mut self) -> Option<Option<usize>> {
+        let index = self.next_var;
+        self.next_var += 1;
+
+        if self.var_fields == 1 {
+            return Some(Some(self.fixed_pos));
+        }
+
+        let table_start = self.fixed_pos;
+        let var_block_start = table_start + 4 * self.var_fields;
+        if index >= self.var_fields || self.data.len() < var_block_start {
+            return None;
+        }
+        self.end = self.end.max(var_block_start);
+
+        let offset = read_i32_le(self.data, table_start + 4 * index);
+        if offset < 0 {
+            return Some(None);
+        }
+        Some(Some(var_block_start + offset as usize))
+    }
+
+    fn read_var_bytes(&mut self, pos: usize) -> Option<&'a [u8]> {
+        if pos >= self.data.len() {
+            return None;
+        }
+        let (len, sz) = read_varint(&self.data[pos..])?;
+        let end = (pos + sz).checked_add(len as usize)?;
+        let bytes = self.data.get(pos + sz..end)?;
+        self.end = self.end.max(end);
+        Some(bytes)
+    }
+
+    pub fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?).ok()
+    }
+
+    pub fn opt_string(&mut self) -> Option<Option<String>> {
+        match self.opt_bytes()? {
+            Some(bytes) => String::from_utf8(bytes).ok().map(Some),
+            None => Some(None),
+        }
+    }
+
+    pub fn bytes(&mut self) -> Option<Vec<u8>> {
+        let pos = self.next_var_pos()??;
+        self.read_var_bytes(pos).map(<[u8]>::to_vec)
+    }
+
+    pub fn opt_bytes(&mut self) -> Option<Option<Vec<u8>>> {
+        self.opt_raw(|data| {
+            let (len, sz) = read_varint(data)?;
+            let end = sz.checked_add(len as usize)?;
+            Some((data.get(sz..end)?.to_vec(), end))
+        })
+    }
+
+    /// Campo variável aninhado; `read` recebe o resto do buffer e retorna (valor, bytes consumidos)
+    pub fn raw<T>(&mut self, read: impl FnOnce(&'a [u8]) -> Option<(T, usize)>) -> Option<T> {
+        let pos = self.next_var_pos()??;
+        let (value, consumed) = read(self.data.get(pos..)?)?;
+        self.end = self.end.max(pos + consumed);
+        Some(value)
+    }
+
+    pub fn opt_raw<T>(&mut self, read: impl FnOnce(&'a [u8]) -> Option<(T, usize)>) -> Option<Option<T>> {
+        let present = self.null_bit()?;
+        let pos = self.next_var_pos()?;
+        match (present, pos) {
+            (true, Some(pos)) => {
+                let (value, consumed) = read(self.data.get(pos..)?)?;
+                self.end = self.end.max(pos + consumed);
+                Some(Some(value))
+            }
+            // Null bit desligado: campo ausente (com 2+ campos o offset é -1)
+            (false, _) => Some(None),
+            (true, None) => None,
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Campos genéricos (usados pelo derive HytalePacket)
+    // ------------------------------------------------------------------------
+
+    pub fn fixed<T: FixedCodec>(&mut self) -> Option<T> {
+        T::read_fixed(self.take_fixed(T::SIZE)?)
+    }
+
+    pub fn opt_fixed<T: FixedCodec>(&mut self) -> Option<Option<T>> {
+        self.nullable_fixed(T::SIZE, T::read_fixed)
+    }
+
+    pub fn var<T: VarCodec>(&mut self) -> Option<T> {
+        self.raw(T::read_var)
+    }
+
+    pub fn opt_var<T: VarCodec>(&mut self) -> Option<Option<T>> {
+        self.opt_raw(T::read_var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_roundtrip() {
+        for value in [0, 1, 127, 128, 16383, 16384, u32::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&buf), Some((value, varint_size(value))));
+        }
+    }
+
+    #[test]
+    fn varint_rejects_empty_unterminated_and_overflow() {
+        assert_eq!(read_varint(&[]), None);
+        assert_eq!(read_varint(&[0x80, 0x80]), None);
+        assert_eq!(read_varint(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F]), None);
+        assert_eq!(read_varint(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]), None);
+    }
+
+    #[test]
+    fn array_count_larger_than_buffer_is_rejected() {
+        let mut buf = Vec::new();
+        write_varint(&mut buf, u32::MAX);
+        assert_eq!(Vec::<String>::read_var(&buf), None);
+        assert_eq!(Vec::<u8>::read_var(&buf), None);
+    }
 }
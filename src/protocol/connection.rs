@@ -0,0 +1,438 @@
+//! Connection state machine (Handshake → Authenticating → Setup → Playing)
+
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use uuid::Uuid;
+use tracing::{debug, info, warn};
+use crate::auth::AuthClient;
+use crate::utils::x509_fingerprint;
+use super::constants::*;
+use super::codec::*;
+use super::packets::*;
+use super::handler::{
+    handle_auth_grant, handle_connect_accept, handle_password_challenge, handle_password_rejected, handle_server_auth_token,
+};
+use super::setup::{handle_join_world, handle_world_load_finished, handle_world_settings};
+use super::versions::ProtocolVersion;
+use super::keepalive::{spawn_reader, Inbound, Latency, PacketSender};
+use super::error::ProtocolError;
+
+/// Código de erro de aplicação usado ao fechar a conexão QUIC por vontade do bot
+pub const CLIENT_DISCONNECT_CODE: quinn::VarInt = quinn::VarInt::from_u32(0);
+
+// ============================================================================
+// Fases
+// ============================================================================
+
+/// Fase atual da conexão, espelhando os handlers do servidor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Stream aberto, Connect ainda não enviado
+    Handshake,
+    /// Connect enviado, trocando AuthGrant/AuthToken/ServerAuthToken (e a senha, se houver)
+    Authenticating,
+    /// Autenticado, recebendo WorldSettings e assets até o JoinWorld
+    Setup,
+    /// Dentro do mundo
+    Playing,
+    /// Disconnect enviado pelo bot, conexão fechada
+    Closed,
+}
+
+impl Phase {
+    /// Faixas de Packet IDs que o servidor pode enviar nesta fase
+    fn inbound_packets(self) -> &'static [RangeInclusive<u32>] {
+        match self {
+            Phase::Handshake | Phase::Closed => &[],
+            Phase::Authenticating => &[CONNECTION_PACKETS, AUTH_PACKETS],
+            Phase::Setup => &[CONNECTION_PACKETS, SETUP_PACKETS, PACKET_JOIN_WORLD..=PACKET_JOIN_WORLD],
+            // Assets podem ser atualizados com o jogador já no mundo
+            Phase::Playing => &[CONNECTION_PACKETS, SETUP_PACKETS, PLAYING_PACKETS],
+        }
+    }
+
+    /// Indica se o servidor pode mandar este pacote nesta fase
+    pub fn accepts(self, packet_id: u32) -> bool {
+        self.inbound_packets().iter().any(|range| range.contains(&packet_id))
+    }
+
+    /// Quanto tempo esperar por um pacote antes de desistir
+    fn read_timeout(self) -> Option<Duration> {
+        match self {
+            Phase::Handshake | Phase::Authenticating | Phase::Closed => Some(READ_TIMEOUT),
+            // O servidor pode levar alguns segundos enviando assets durante o Setup
+            Phase::Setup => Some(Duration::from_secs(30)),
+            // No mundo o servidor pode passar muito tempo só mandando Pings (respondidos
+            // em background), conexão morta é detectada pelo idle timeout do QUIC
+            Phase::Playing => None,
+        }
+    }
+}
+
+/// Tokens do session service usados na autenticação mútua
+#[derive(Clone, Copy)]
+struct SessionCredentials<'a> {
+    client: &'a AuthClient,
+    identity_token: &'a str,
+    session_token: &'a str,
+    x509_fingerprint: &'a str,
+}
+
+/// O que fazer depois de despachar um pacote
+enum Flow {
+    Continue,
+    Advance(Phase),
+}
+
+// ============================================================================
+// Connection
+// ============================================================================
+
+/// Conexão com um servidor Hytale sobre um stream bidirecional QUIC
+pub struct Connection {
+    // Conexão QUIC por baixo dos streams, fechada com código de erro no disconnect
+    quic: quinn::Connection,
+    out: PacketSender,
+    inbound: mpsc::UnboundedReceiver<Inbound>,
+    reader: JoinHandle<()>,
+    latency: Arc<Mutex<Latency>>,
+    version: &'static ProtocolVersion,
+    phase: Phase,
+    world: Option<JoinWorldPacket>,
+    last_disconnect: Option<DisconnectPacket>,
+    // Senha do servidor, usada só se ele mandar um desafio
+    password: Option<String>,
+    // Fingerprint do certificado que o servidor apresentou no handshake TLS
+    server_fingerprint: Option<String>,
+}
+
+impl Connection {
+    /// Abre o stream bidirecional do protocolo numa conexão QUIC já estabelecida
+    pub async fn open(quic: quinn::Connection, version: &'static ProtocolVersion) -> Result<Self, ProtocolError> {
+        let (send, recv) = quic.open_bi().await
+            .map_err(ProtocolError::Connection)?;
+
+        let server_fingerprint = quic.peer_identity()
+            .and_then(|identity| identity.downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>().ok())
+            .and_then(|chain| chain.first().map(|cert| x509_fingerprint(cert)));
+
+        let out = PacketSender::spawn(send, version);
+        let latency = Arc::new(Mutex::new(Latency::default()));
+        let (inbound, reader) = spawn_reader(recv, quic.clone(), out.clone(), latency.clone());
+
+        Ok(Self {
+            quic,
+            out,
+            inbound,
+            reader,
+            latency,
+            version,
+            phase: Phase::Handshake,
+            world: None,
+            last_disconnect: None,
+            password: None,
+            server_fingerprint,
+        })
+    }
+
+    /// Senha para servidores protegidos, respondida no PasswordChallenge
+    pub fn set_password(&mut self, password: impl Into<String>) -> &mut Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Versão do protocolo usada nesta conexão
+    pub fn version(&self) -> &'static ProtocolVersion {
+        self.version
+    }
+
+    /// Fingerprint `x5t#S256` do certificado do servidor nesta conexão
+    pub fn server_fingerprint(&self) -> Option<&str> {
+        self.server_fingerprint.as_deref()
+    }
+
+    /// Latência medida pelos Pings respondidos até agora
+    pub fn latency(&self) -> Latency {
+        *self.latency.lock().unwrap()
+    }
+
+    /// Disconnect recebido do servidor, se houve um
+    pub fn last_disconnect(&self) -> Option<&DisconnectPacket> {
+        self.last_disconnect.as_ref()
+    }
+
+    /// Indica se o servidor desconectou por protocolo incompatível
+    ///
+    /// Nesse caso vale tentar de novo com outra versão conhecida.
+    pub fn rejected_as_outdated(&self) -> bool {
+        self.last_disconnect.as_ref().is_some_and(|d| d.kind() == DisconnectKind::OutdatedClient)
+    }
+
+    /// Envia o Connect e passa para a fase de autenticação
+    ///
+    /// Sem `identity_token` o servidor só aceita o bot se estiver em `--auth-mode insecure`.
+    pub async fn handshake(&mut self, username: &str, uuid: Uuid, identity_token: Option<&str>) -> Result<(), ProtocolError> {
+        self.expect_phase(Phase::Handshake)?;
+
+        if username.len() > MAX_USERNAME_LENGTH {
+            return Err(ProtocolError::UsernameTooLong { max: MAX_USERNAME_LENGTH });
+        }
+
+        let mut packet = ConnectPacket::new(username, uuid, identity_token);
+        packet.protocol_hash = self.version.hash.to_string();
+        self.out.send(&packet)?;
+
+        self.phase = Phase::Authenticating;
+        Ok(())
+    }
+
+    /// Avisa o servidor que o bot está saindo e fecha a conexão QUIC
+    ///
+    /// Espera o servidor confirmar o Disconnect antes do CONNECTION_CLOSE, que
+    /// descartaria o que ainda estivesse em trânsito. Quem chama deve esperar o
+    /// `Endpoint::wait_idle` para o fechamento em si chegar ao servidor.
+    ///
+    /// A conexão fica na fase `Closed` mesmo se o envio do Disconnect falhar.
+    pub async fn disconnect(&mut self, reason: &str) -> Result<(), ProtocolError> {
+        if self.phase == Phase::Closed {
+            return Ok(());
+        }
+
+        // Antes do Connect o servidor ainda não conhece o bot, basta fechar
+        let sent = if self.phase == Phase::Handshake {
+            Ok(())
+        } else {
+            self.out.send(&DisconnectPacket::new(reason))
+        };
+
+        self.out.finish().await;
+        self.quic.close(CLIENT_DISCONNECT_CODE, reason.as_bytes());
+        self.reader.abort();
+        self.phase = Phase::Closed;
+
+        sent
+    }
+
+    /// Conduz a autenticação mútua até o servidor liberar o Setup
+    pub async fn authenticate(
+        &mut self,
+        client: &AuthClient,
+        identity_token: &str,
+        session_token: &str,
+        x509_fingerprint: &str,
+    ) -> Result<(), ProtocolError> {
+        let credentials = SessionCredentials { client, identity_token, session_token, x509_fingerprint };
+        self.authenticate_with(Some(credentials)).await
+    }
+
+    /// Fluxo de desenvolvimento de servidores em `--auth-mode insecure`
+    ///
+    /// Não usa o session service: o servidor responde o Connect direto com ConnectAccept.
+    pub async fn authenticate_insecure(&mut self) -> Result<(), ProtocolError> {
+        self.authenticate_with(None).await
+    }
+
+    async fn authenticate_with(&mut self, credentials: Option<SessionCredentials<'_>>) -> Result<(), ProtocolError> {
+        self.expect_phase(Phase::Authenticating)?;
+
+        loop {
+            let (packet_id, payload) = self.read_packet().await?;
+            let flow = self.dispatch_authenticating(packet_id, &payload, credentials).await?;
+
+            if let Flow::Advance(phase) = flow {
+                self.phase = phase;
+                return Ok(());
+            }
+        }
+    }
+
+    /// Conduz a fase de Setup até o servidor mandar o JoinWorld
+    pub async fn setup(&mut self) -> Result<&JoinWorldPacket, ProtocolError> {
+        self.expect_phase(Phase::Setup)?;
+
+        loop {
+            let (packet_id, payload) = self.read_packet().await?;
+
+            // O JoinWorld encerra o Setup
+            if packet_id == PACKET_JOIN_WORLD {
+                let world = handle_join_world(&self.out, &payload)?;
+                self.phase = Phase::Playing;
+                return Ok(self.world.insert(world));
+            }
+
+            self.dispatch_setup(packet_id, &payload)?;
+        }
+    }
+
+    /// Mantém o bot no mundo até o servidor desconectar
+    pub async fn play(&mut self) -> Result<(), ProtocolError> {
+        self.expect_phase(Phase::Playing)?;
+
+        loop {
+            let (packet_id, payload) = self.read_packet().await?;
+            self.dispatch_playing(packet_id, &payload)?;
+        }
+    }
+
+    // ========================================================================
+    // Tabelas de despacho por fase
+    // ========================================================================
+
+    /// Pacotes de conexão, válidos em qualquer fase
+    fn dispatch_common(&mut self, packet_id: u32, payload: &[u8]) -> Result<Flow, ProtocolError> {
+        match packet_id {
+            PACKET_DISCONNECT => {
+                // Payload malformado ainda é uma desconexão, só sem motivo
+                let disconnect = DisconnectPacket::decode(payload).unwrap_or(DisconnectPacket {
+                    disconnect_type: DisconnectType::Disconnect,
+                    reason: None,
+                });
+                let error = ProtocolError::ServerDisconnect {
+                    reason: disconnect.reason_text().to_string(),
+                    disconnect_type: disconnect.disconnect_type,
+                    kind: disconnect.kind(),
+                };
+                warn!("⛔ Servidor desconectou! (fase: {:?}, {:?}: {})", self.phase, disconnect.kind(), disconnect.reason_text());
+
+                self.last_disconnect = Some(disconnect);
+                Err(error)
+            }
+
+            // Pings são respondidos pela task de leitura, nunca chegam aqui
+            _ => Ok(Flow::Continue),
+        }
+    }
+
+    async fn dispatch_authenticating(
+        &mut self,
+        packet_id: u32,
+        payload: &[u8],
+        credentials: Option<SessionCredentials<'_>>,
+    ) -> Result<Flow, ProtocolError> {
+        match packet_id {
+            PACKET_AUTH_GRANT => {
+                // Servidor autenticado respondendo um Connect sem identity token
+                let credentials = credentials.ok_or(ProtocolError::AuthRequired)?;
+                handle_auth_grant(
+                    &self.out,
+                    credentials.client,
+                    payload,
+                    credentials.identity_token,
+                    credentials.session_token,
+                    credentials.x509_fingerprint,
+                    self.server_fingerprint.as_deref(),
+                )
+                .await?;
+                Ok(Flow::Continue)
+            }
+
+            PACKET_SERVER_AUTH_TOKEN => {
+                let challenge = handle_server_auth_token(payload, self.server_fingerprint.as_deref())?;
+                self.continue_with_password(challenge)
+            }
+
+            PACKET_PASSWORD_ACCEPTED => {
+                info!("🔓 Senha aceita pelo servidor!");
+                Ok(Flow::Advance(Phase::Setup))
+            }
+
+            PACKET_PASSWORD_REJECTED => Err(handle_password_rejected(payload)),
+
+            // Resposta ao Connect no fluxo de desenvolvimento
+            PACKET_CONNECT_ACCEPT => {
+                let challenge = handle_connect_accept(payload)?;
+                self.continue_with_password(challenge)
+            }
+
+            PACKET_DISCONNECT => self.dispatch_common(packet_id, payload),
+
+            _ => {
+                debug!("❓ Pacote desconhecido durante Auth: {} (ID: {})", get_packet_name(packet_id), packet_id);
+                Ok(Flow::Continue)
+            }
+        }
+    }
+
+    fn dispatch_setup(&mut self, packet_id: u32, payload: &[u8]) -> Result<Flow, ProtocolError> {
+        match packet_id {
+            PACKET_WORLD_SETTINGS => {
+                handle_world_settings(&self.out, payload)?;
+                Ok(Flow::Continue)
+            }
+
+            PACKET_WORLD_LOAD_FINISHED => {
+                handle_world_load_finished(&self.out)?;
+                Ok(Flow::Continue)
+            }
+
+            // Assets e configs do mundo não interessam ao bot
+            _ => self.dispatch_common(packet_id, payload),
+        }
+    }
+
+    fn dispatch_playing(&mut self, packet_id: u32, payload: &[u8]) -> Result<Flow, ProtocolError> {
+        self.dispatch_common(packet_id, payload)
+    }
+
+    // ========================================================================
+    // Helpers
+    // ========================================================================
+
+    /// Responde o desafio de senha, ou libera o Setup se o servidor não pediu senha
+    fn continue_with_password(&self, challenge: Option<Vec<u8>>) -> Result<Flow, ProtocolError> {
+        match challenge {
+            Some(challenge) => {
+                handle_password_challenge(&self.out, &challenge, self.password.as_deref())?;
+                Ok(Flow::Continue)
+            }
+            None => Ok(Flow::Advance(Phase::Setup)),
+        }
+    }
+
+    /// Lê o próximo pacote, rejeitando os que não pertencem à fase atual
+    ///
+    /// O ID retornado já está convertido para a versão atual do protocolo.
+    async fn read_packet(&mut self) -> Result<(u32, Vec<u8>), ProtocolError> {
+        let next = match self.phase.read_timeout() {
+            Some(limit) => timeout(limit, self.inbound.recv())
+                .await
+                .map_err(|_| ProtocolError::Timeout { phase: self.phase })?,
+            None => self.inbound.recv().await,
+        };
+
+        // A task de leitura manda o erro que a encerrou antes de fechar o canal
+        let (packet_id, payload) = next.ok_or(ProtocolError::StreamClosed)??;
+
+        if !self.version.supports(packet_id) {
+            return Err(ProtocolError::UnsupportedPacket { packet_id, version: self.version.version });
+        }
+
+        if !self.phase.accepts(packet_id) {
+            return Err(ProtocolError::IllegalPacket { packet_id, phase: self.phase });
+        }
+
+        Ok((packet_id, payload))
+    }
+
+    fn expect_phase(&self, expected: Phase) -> Result<(), ProtocolError> {
+        if self.phase != expected {
+            return Err(ProtocolError::WrongPhase { expected, actual: self.phase });
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // A task de leitura segura a conexão QUIC, não pode sobreviver à Connection
+        self.reader.abort();
+    }
+}
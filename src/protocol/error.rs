@@ -0,0 +1,99 @@
+//! Protocol errors
+
+use thiserror::Error;
+use crate::auth::AuthError;
+use super::connection::Phase;
+use super::constants::get_packet_name;
+use super::packets::{DisconnectKind, DisconnectType};
+
+/// Falhas na conversa com o servidor depois que a conexão QUIC está aberta
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Timeout esperando pacote (fase {phase:?})")]
+    Timeout { phase: Phase },
+
+    #[error("Erro lendo do stream: {0}")]
+    Read(#[from] quinn::ReadExactError),
+
+    #[error("Erro escrevendo no stream: {0}")]
+    Write(#[from] quinn::WriteError),
+
+    #[error("Stream do protocolo fechado")]
+    StreamClosed,
+
+    #[error("Conexão QUIC perdida: {0}")]
+    Connection(#[from] quinn::ConnectionError),
+
+    #[error("Pacote {} (ID: {packet_id}) truncado: esperava {needed} bytes, recebeu {got}", get_packet_name(*packet_id))]
+    Truncated { packet_id: u32, needed: usize, got: usize },
+
+    #[error("Pacote {} (ID: {packet_id}) grande demais: {size} bytes, máximo {max}", get_packet_name(*packet_id))]
+    FrameTooLarge { packet_id: u32, size: usize, max: usize },
+
+    #[error("Erro descomprimindo pacote {} (ID: {packet_id}): {source}", get_packet_name(*packet_id))]
+    Decompress {
+        packet_id: u32,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Erro comprimindo pacote {} (ID: {packet_id}): {source}", get_packet_name(*packet_id))]
+    Compress {
+        packet_id: u32,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Falha ao parsear {} (ID: {packet_id})", get_packet_name(*packet_id))]
+    Malformed { packet_id: u32 },
+
+    #[error("{} sem {field}", get_packet_name(*packet_id))]
+    MissingField { packet_id: u32, field: &'static str },
+
+    #[error("Pacote {} (ID: {packet_id}) ilegal na fase {phase:?}", get_packet_name(*packet_id))]
+    IllegalPacket { packet_id: u32, phase: Phase },
+
+    #[error("Pacote {} não suportado na versão {version}", get_packet_name(*packet_id))]
+    UnsupportedPacket { packet_id: u32, version: &'static str },
+
+    #[error("Versão do protocolo desconhecida: {0}")]
+    UnknownVersion(String),
+
+    #[error("Operação requer fase {expected:?}, conexão está em {actual:?}")]
+    WrongPhase { expected: Phase, actual: Phase },
+
+    #[error("Username muito longo! Máximo {max} caracteres.")]
+    UsernameTooLong { max: usize },
+
+    #[error("Servidor exige autenticação, mas o bot conectou em modo insecure")]
+    AuthRequired,
+
+    #[error("Servidor pediu senha, mas nenhuma foi configurada")]
+    PasswordRequired,
+
+    #[error("Senha recusada pelo servidor ({attempts_remaining} tentativas restantes)")]
+    PasswordRejected { attempts_remaining: i32 },
+
+    #[error("Token do servidor em {} preso a outro certificado: token {bound}, conexão {actual}", get_packet_name(*packet_id))]
+    ServerCertificateMismatch { packet_id: u32, bound: String, actual: String },
+
+    #[error("Desconectado pelo servidor ({kind:?}): {reason}")]
+    ServerDisconnect {
+        reason: String,
+        disconnect_type: DisconnectType,
+        kind: DisconnectKind,
+    },
+
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+}
+
+impl ProtocolError {
+    /// Categoria do Disconnect, se o erro veio de uma desconexão pelo servidor
+    pub fn disconnect_kind(&self) -> Option<DisconnectKind> {
+        match self {
+            ProtocolError::ServerDisconnect { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
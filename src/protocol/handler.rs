@@ -1,91 +1,51 @@
 //! Auth flow handler
 
-use std::error::Error;
-use quinn::{SendStream, RecvStream};
-use super::constants::*;
-use super::codec::*;
+use tracing::{info, warn};
 use super::packets::*;
-use crate::auth::{exchange_grant_for_access_token, request_server_auth_grant};
-use crate::utils::extract_jwt_subject;
-
-type BoxError = Box<dyn Error + Send + Sync>;
-
-// ============================================================================
-// Estruturas de Pacotes
-// ============================================================================
-
-#[derive(Debug)]
-pub struct AuthGrantPacket {
-    pub authorization_grant: Option<String>,
-    pub server_identity_token: Option<String>,
-}
-
-#[derive(Debug)]
-pub struct ServerAuthTokenPacket {
-    pub server_access_token: Option<String>,
-    pub password_challenge: Option<Vec<u8>>,
-}
-
-// ============================================================================
-// Parsing de Pacotes
-// ============================================================================
-
-pub fn parse_auth_grant(data: &[u8]) -> Option<AuthGrantPacket> {
-    if data.len() < 9 { return None; }
-
-    const VAR_BLOCK_START: usize = 9;
-    let offset_grant = read_i32_le(data, 1);
-    let offset_sit = read_i32_le(data, 5);
-
-    Some(AuthGrantPacket {
-        authorization_grant: read_varstring_at(data, VAR_BLOCK_START, offset_grant),
-        server_identity_token: read_varstring_at(data, VAR_BLOCK_START, offset_sit),
-    })
-}
-
-pub fn parse_server_auth_token(data: &[u8]) -> Option<ServerAuthTokenPacket> {
-    if data.len() < 9 { return None; }
-
-    const VAR_BLOCK_START: usize = 9;
-    let offset_sat = read_i32_le(data, 1);
-    let offset_pwd = read_i32_le(data, 5);
-
-    Some(ServerAuthTokenPacket {
-        server_access_token: read_varstring_at(data, VAR_BLOCK_START, offset_sat),
-        password_challenge: read_varbytes_at(data, VAR_BLOCK_START, offset_pwd),
-    })
-}
+use super::keepalive::PacketSender;
+use super::constants::*;
+use super::error::ProtocolError;
+use crate::auth::{exchange_grant_for_access_token, request_server_auth_grant, AuthClient};
+use crate::utils::{extract_jwt_cert_thumbprint, extract_jwt_subject, fingerprints_match};
 
 // ============================================================================
 // Handlers de Pacotes Individuais
 // ============================================================================
 
-async fn handle_auth_grant(
-    send: &mut SendStream,
+pub(super) async fn handle_auth_grant(
+    out: &PacketSender,
+    client: &AuthClient,
     payload: &[u8],
     identity_token: &str,
     session_token: &str,
     x509_fingerprint: &str,
-) -> Result<(), BoxError> {
-    let auth_grant = parse_auth_grant(payload)
-        .ok_or("Falha ao parsear AuthGrant")?;
+    server_fingerprint: Option<&str>,
+) -> Result<(), ProtocolError> {
+    let auth_grant = AuthGrantPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_AUTH_GRANT })?;
+
+    check_server_binding(PACKET_AUTH_GRANT, auth_grant.server_identity_token.as_deref(), server_fingerprint)?;
 
     let grant = auth_grant.authorization_grant
-        .ok_or("AuthGrant sem authorization_grant")?;
+        .ok_or(ProtocolError::MissingField { packet_id: PACKET_AUTH_GRANT, field: "authorization_grant" })?;
 
     // Trocar grant por access token
-    let access_token = exchange_grant_for_access_token(&grant, session_token, x509_fingerprint).await?;
+    let access_token = exchange_grant_for_access_token(client, &grant, session_token, x509_fingerprint).await?;
 
     // Gerar grant para o servidor (se tiver serverIdentityToken)
-    let server_grant = generate_server_grant(&auth_grant.server_identity_token, identity_token, session_token).await;
+    let server_grant = generate_server_grant(client, &auth_grant.server_identity_token, identity_token, session_token).await;
 
-    let auth_token = build_auth_token(Some(&access_token), server_grant.as_deref());
-    send.write_all(&auth_token).await?;
+    let auth_token = AuthTokenPacket {
+        access_token: Some(access_token),
+        server_authorization_grant: server_grant,
+    };
+    out.send(&auth_token)?;
 
     Ok(())
 }
 
 async fn generate_server_grant(
+    client: &AuthClient,
     server_identity_token: &Option<String>,
     identity_token: &str,
     session_token: &str,
@@ -93,95 +53,77 @@ async fn generate_server_grant(
     let server_identity = server_identity_token.as_ref()?;
 
     let server_uuid = extract_jwt_subject(server_identity).or_else(|| {
-        println!("⚠️ Não foi possível extrair UUID do serverIdentityToken");
+        warn!("⚠️ Não foi possível extrair UUID do serverIdentityToken");
         None
     })?;
 
-    match request_server_auth_grant(identity_token, &server_uuid, session_token).await {
+    match request_server_auth_grant(client, identity_token, &server_uuid, session_token).await {
         Ok(grant) => Some(grant),
         Err(e) => {
-            println!("⚠️ Falha ao gerar grant para servidor: {}", e);
+            warn!("⚠️ Falha ao gerar grant para servidor: {}", e);
             None
         }
     }
 }
 
-fn handle_server_auth_token(payload: &[u8]) -> Result<bool, BoxError> {
-    println!("🔐 Recebido ServerAuthToken - autenticação avançando!");
+/// Retorna o desafio de senha, se o servidor pediu um
+pub(super) fn handle_server_auth_token(payload: &[u8], server_fingerprint: Option<&str>) -> Result<Option<Vec<u8>>, ProtocolError> {
+    info!("🔐 Recebido ServerAuthToken - autenticação avançando!");
 
-    let server_auth = parse_server_auth_token(payload)
-        .ok_or("Falha ao parsear ServerAuthToken")?;
+    let server_auth = ServerAuthTokenPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_SERVER_AUTH_TOKEN })?;
 
-    if server_auth.password_challenge.is_some() {
-        println!("⚠️ Servidor pediu senha (PasswordChallenge), não implementado.");
-        return Ok(false); // Continuar loop
-    }
+    // O access token do servidor fica preso ao certificado dele, como o nosso
+    check_server_binding(PACKET_SERVER_AUTH_TOKEN, server_auth.server_access_token.as_deref(), server_fingerprint)?;
 
-    Ok(true) // Auth completa
+    Ok(server_auth.password_challenge)
 }
 
-async fn handle_ping(send: &mut SendStream, payload: &[u8]) -> Result<(), BoxError> {
-    println!("🏓 Ping recebido durante handshake, respondendo Pong...");
+/// Confere o claim `cnf` de um token do servidor com o certificado que ele apresentou no TLS
+///
+/// Tokens sem `cnf` passam: não há com o que comparar.
+fn check_server_binding(packet_id: u32, token: Option<&str>, server_fingerprint: Option<&str>) -> Result<(), ProtocolError> {
+    let (Some(bound), Some(actual)) = (token.and_then(extract_jwt_cert_thumbprint), server_fingerprint) else {
+        return Ok(());
+    };
 
-    if payload.len() < 17 {
-        return Err("Payload de Ping muito curto".into());
+    if !fingerprints_match(&bound, actual) {
+        return Err(ProtocolError::ServerCertificateMismatch { packet_id, bound, actual: actual.to_string() });
     }
+    Ok(())
+}
 
-    let ping_id = u32::from_le_bytes([payload[1], payload[2], payload[3], payload[4]]);
-    let seconds = i64::from_le_bytes([
-        payload[5], payload[6], payload[7], payload[8],
-        payload[9], payload[10], payload[11], payload[12]
-    ]);
-    let nanos = i32::from_le_bytes([payload[13], payload[14], payload[15], payload[16]]);
+/// Retorna o desafio de senha, se o servidor pediu um
+pub(super) fn handle_connect_accept(payload: &[u8]) -> Result<Option<Vec<u8>>, ProtocolError> {
+    info!("🎉 ConnectAccept - Conexão totalmente aceita!");
 
-    let pong = build_pong_packet(ping_id, seconds, nanos);
-    send.write_all(&pong).await?;
+    let accept = ConnectAcceptPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_CONNECT_ACCEPT })?;
 
-    Ok(())
+    Ok(accept.password_challenge)
 }
 
 // ============================================================================
-// Loop Principal de Auth
+// Senha do Servidor
 // ============================================================================
 
-pub async fn handle_auth_flow_network(
-    send: &mut SendStream,
-    recv: &mut RecvStream,
-    identity_token: &str,
-    session_token: &str,
-    x509_fingerprint: &str,
-) -> Result<(), BoxError> {
-    loop {
-        let (packet_id, payload) = read_packet(recv).await?;
-
-        match packet_id {
-            PACKET_DISCONNECT => {
-                println!("⛔ Servidor desconectou!");
-                return Err("Desconectado pelo servidor".into());
-            }
-
-            PACKET_AUTH_GRANT => {
-                handle_auth_grant(send, &payload, identity_token, session_token, x509_fingerprint).await?;
-            }
-
-            PACKET_SERVER_AUTH_TOKEN => {
-                if handle_server_auth_token(&payload)? {
-                    return Ok(());
-                }
-            }
-
-            PACKET_CONNECT_ACCEPT => {
-                println!("🎉 ConnectAccept - Conexão totalmente aceita!");
-                return Ok(());
-            }
-
-            PACKET_PING => {
-                handle_ping(send, &payload).await?;
-            }
-
-            _ => {
-                println!("❓ Pacote desconhecido durante Auth: {} (ID: {})", get_packet_name(packet_id), packet_id);
-            }
-        }
+pub(super) fn handle_password_challenge(
+    out: &PacketSender,
+    challenge: &[u8],
+    password: Option<&str>,
+) -> Result<(), ProtocolError> {
+    let password = password.ok_or(ProtocolError::PasswordRequired)?;
+
+    info!("🔑 Servidor pediu senha, respondendo desafio...");
+    out.send(&PasswordResponsePacket::new(challenge, password))?;
+
+    Ok(())
+}
+
+/// A senha configurada não muda, então tentar de novo com o novo desafio não adianta
+pub(super) fn handle_password_rejected(payload: &[u8]) -> ProtocolError {
+    match PasswordRejectedPacket::decode(payload) {
+        Some(rejected) => ProtocolError::PasswordRejected { attempts_remaining: rejected.attempts_remaining },
+        None => ProtocolError::Malformed { packet_id: PACKET_PASSWORD_REJECTED },
     }
 }
@@ -0,0 +1,208 @@
+//! Background packet I/O tasks and Ping/Pong responder
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use quinn::{RecvStream, SendStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use super::codec::read_packet;
+use super::constants::*;
+use super::error::ProtocolError;
+use super::packets::*;
+use super::versions::ProtocolVersion;
+
+/// Pacote recebido já convertido para a versão atual, ou o erro que encerrou a leitura
+pub(super) type Inbound = Result<(u32, Vec<u8>), ProtocolError>;
+
+// ============================================================================
+// Latência
+// ============================================================================
+
+/// Medições de latência atualizadas a cada Ping do servidor
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Latency {
+    /// RTT estimado pelo transporte QUIC no momento do último Ping, não pelo Ping/Pong
+    pub quic_rtt: Duration,
+    /// Round trip do Ping/Pong medido pelo servidor com os nossos Pongs, como ele os reporta no Ping
+    pub server_raw: i32,
+    pub server_direct: i32,
+    pub server_tick: i32,
+    pub pings_answered: u64,
+    pub last_ping_at: Option<Instant>,
+}
+
+// ============================================================================
+// Envio
+// ============================================================================
+
+/// Quanto esperar o servidor confirmar os últimos pacotes antes de fechar a conexão
+const FINISH_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum Outbound {
+    Frame(Vec<u8>),
+    /// Escreve o que está na fila, fecha o stream e avisa quando terminar
+    Finish(oneshot::Sender<()>),
+}
+
+/// Fila de envio do stream, codifica os pacotes na versão da conexão
+///
+/// Uma task dedicada escreve os frames, então o Pong não espera o bot terminar
+/// o que estiver fazendo (ex: chamadas HTTP durante a autenticação).
+#[derive(Clone)]
+pub(super) struct PacketSender {
+    tx: mpsc::UnboundedSender<Outbound>,
+    queued: Arc<AtomicUsize>,
+    version: &'static ProtocolVersion,
+}
+
+impl PacketSender {
+    pub(super) fn spawn(send: SendStream, version: &'static ProtocolVersion) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(write_loop(send, rx, queued.clone()));
+
+        Self { tx, queued, version }
+    }
+
+    pub(super) fn send<P: Packet>(&self, packet: &P) -> Result<(), ProtocolError> {
+        let frame = self.version.frame(packet)?;
+
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        self.tx.send(Outbound::Frame(frame)).map_err(|_| {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            ProtocolError::StreamClosed
+        })
+    }
+
+    /// Pacotes na fila esperando para serem escritos no stream
+    pub(super) fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Espera a fila esvaziar, encerra o lado de escrita do stream e espera o
+    /// servidor confirmar o recebimento (até `FINISH_TIMEOUT`)
+    pub(super) async fn finish(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(Outbound::Finish(done_tx)).is_ok() {
+            // Task de escrita já morta também serve: não há mais o que enviar
+            let _ = done_rx.await;
+        }
+    }
+}
+
+async fn write_loop(mut send: SendStream, mut rx: mpsc::UnboundedReceiver<Outbound>, queued: Arc<AtomicUsize>) {
+    while let Some(outbound) = rx.recv().await {
+        match outbound {
+            Outbound::Frame(frame) => {
+                let result = send.write_all(&frame).await;
+                queued.fetch_sub(1, Ordering::SeqCst);
+
+                // O leitor percebe a conexão caindo e reporta o erro
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            Outbound::Finish(done) => {
+                // Stream já fechado pelo servidor não impede o resto do encerramento
+                if send.finish().is_ok() {
+                    // Fechar a conexão antes do ACK descarta o que ainda não chegou (ex: o Disconnect)
+                    let _ = tokio::time::timeout(FINISH_TIMEOUT, send.stopped()).await;
+                }
+                let _ = done.send(());
+                break;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Recebimento
+// ============================================================================
+
+/// Lê o stream durante toda a sessão, respondendo Pings na hora
+///
+/// Os outros pacotes seguem para a `Connection` com o ID da versão atual.
+pub(super) fn spawn_reader(
+    recv: RecvStream,
+    quic: quinn::Connection,
+    out: PacketSender,
+    latency: Arc<Mutex<Latency>>,
+) -> (mpsc::UnboundedReceiver<Inbound>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(read_loop(recv, quic, out, latency, tx));
+    (rx, handle)
+}
+
+async fn read_loop(
+    mut recv: RecvStream,
+    quic: quinn::Connection,
+    out: PacketSender,
+    latency: Arc<Mutex<Latency>>,
+    tx: mpsc::UnboundedSender<Inbound>,
+) {
+    loop {
+        let (wire_id, payload) = match read_packet(&mut recv, out.version).await {
+            Ok(packet) => packet,
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+        };
+
+        // Pacote que só existe na versão do servidor, o bot não tem o que fazer com ele
+        let Some(packet_id) = out.version.from_wire(wire_id) else {
+            continue;
+        };
+
+        let inbound = if packet_id == PACKET_PING {
+            match answer_ping(&out, &payload, &quic, &latency) {
+                Ok(()) => continue,
+                Err(e) => Err(e),
+            }
+        } else {
+            Ok((packet_id, payload))
+        };
+
+        // Connection descartada, ninguém mais lê
+        if tx.send(inbound).is_err() {
+            return;
+        }
+    }
+}
+
+/// Responde um Ping com os três Pongs que o cliente oficial manda
+///
+/// O cliente responde Raw na thread de rede, Direct quando processa o pacote e
+/// Tick no próximo tick do jogo. O bot não tem essas etapas, então manda os três juntos.
+fn answer_ping(
+    out: &PacketSender,
+    payload: &[u8],
+    quic: &quinn::Connection,
+    latency: &Mutex<Latency>,
+) -> Result<(), ProtocolError> {
+    let ping = PingPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_PING })?;
+
+    for pong_type in [PongType::Raw, PongType::Direct, PongType::Tick] {
+        let pong = PongPacket {
+            id: ping.id,
+            time: ping.time,
+            pong_type,
+            packet_queue_size: out.queued().min(u16::MAX as usize) as u16,
+        };
+        out.send(&pong)?;
+    }
+
+    let mut latency = latency.lock().unwrap();
+    latency.quic_rtt = quic.rtt();
+    latency.server_raw = ping.last_ping_value_raw;
+    latency.server_direct = ping.last_ping_value_direct;
+    latency.server_tick = ping.last_ping_value_tick;
+    latency.pings_answered += 1;
+    latency.last_ping_at = Some(Instant::now());
+
+    Ok(())
+}
@@ -4,8 +4,17 @@ pub mod constants;
 pub mod codec;
 pub mod packets;
 pub mod handler;
+pub mod setup;
+pub mod connection;
+pub mod keepalive;
+pub mod versions;
+pub mod error;
 
 pub use constants::*;
 pub use codec::*;
 pub use packets::*;
-pub use handler::*;
+pub use connection::*;
+pub use keepalive::Latency;
+pub use versions::ProtocolVersion;
+pub use error::ProtocolError;
+pub use hytale_bot_derive::HytalePacket;
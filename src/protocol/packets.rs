@@ -1,188 +1,442 @@
-//! Packet builders for Hytale protocol
+//! Packet types and codecs for Hytale protocol
 
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
-use super::constants::{PACKET_CONNECT, PACKET_AUTH_TOKEN, PACKET_PONG, PROTOCOL_HASH};
-use super::codec::{write_varint, varint_size};
-
-/// Client types
-#[repr(u8)]
-pub enum ClientType {
-    Game = 0,
-    Editor = 1,
+use super::constants::*;
+use super::codec::*;
+use hytale_bot_derive::HytalePacket;
+
+// ============================================================================
+// Packet Trait
+// ============================================================================
+
+/// Um pacote do protocolo com codificação simétrica do payload
+pub trait Packet: Sized {
+    /// Packet ID escrito no header do frame
+    const ID: u32;
+
+    /// Escreve o payload (sem header) no buffer
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Lê o payload (sem header), retorna None se estiver malformado
+    fn decode(data: &[u8]) -> Option<Self>;
+
+    /// Codifica o pacote já com o header [Length] [ID]
+    fn to_frame(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.encode(&mut payload);
+        frame_packet(Self::ID, payload)
+    }
 }
 
-pub fn build_connect_packet_with_token(username: &str, uuid: Uuid, identity_token: &str) -> Vec<u8> {
+// ============================================================================
+// Tipos Auxiliares
+// ============================================================================
+
+/// Enums serializados como um byte no fixed block
+macro_rules! byte_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($variant:ident = $value:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant = $value),*
+        }
+
+        impl FixedCodec for $name {
+            const SIZE: usize = 1;
+
+            fn write_fixed(&self, buf: &mut Vec<u8>) {
+                buf.push(*self as u8);
+            }
+
+            fn read_fixed(bytes: &[u8]) -> Option<Self> {
+                match *bytes.first()? {
+                    $($value => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
 
-    let identity_token = Some(identity_token);
-    let mut payload = Vec::with_capacity(1024);
+byte_enum! {
+    /// Client types
+    pub enum ClientType {
+        Game = 0,
+        Editor = 1,
+    }
+}
 
-    // ========== FIXED BLOCK (102 bytes) ==========
+byte_enum! {
+    /// Tipo de desconexão enviado junto com o Disconnect
+    pub enum DisconnectType {
+        Disconnect = 0,
+        Crash = 1,
+    }
+}
 
-    // Offset 0: nullBits
-    let null_bits: u8 = if identity_token.is_some() { 0x02 } else { 0x00 };
-    payload.push(null_bits);
+byte_enum! {
+    /// Qual medição de latência o Pong responde
+    pub enum PongType {
+        Raw = 0,
+        Direct = 1,
+        Tick = 2,
+    }
+}
 
-    // Offset 1-64: protocolHash (64 bytes ASCII fixo)
-    payload.extend_from_slice(PROTOCOL_HASH);
+/// Instante (segundos + nanos) como o Java Instant (12 bytes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InstantData {
+    pub seconds: i64,
+    pub nanos: i32,
+}
 
-    // Offset 65: clientType (Game = 0)
-    payload.push(ClientType::Game as u8);
+impl FixedCodec for InstantData {
+    const SIZE: usize = 12;
 
-    // Offset 66-81: UUID (16 bytes)
-    // O UUID é armazenado como dois i64 em Big Endian
-    let uuid_bytes = uuid.as_bytes();
-    payload.extend_from_slice(uuid_bytes);
+    fn write_fixed(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.seconds.to_le_bytes());
+        buf.extend_from_slice(&self.nanos.to_le_bytes());
+    }
 
-    // ========== VARIABLE OFFSETS (20 bytes) ==========
+    fn read_fixed(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            seconds: i64::read_fixed(bytes.get(0..8)?)?,
+            nanos: i32::read_fixed(bytes.get(8..12)?)?,
+        })
+    }
+}
 
-    // Calcular offsets dinamicamente
-    let mut current_offset: i32 = 0;
+/// Endereço de servidor (usado em referrals)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostAddress {
+    pub host: String,
+    pub port: u16,
+}
 
-    // Username será primeiro no variable block
-    let username_bytes = username.as_bytes();
-    if username_bytes.len() > 16 {
-        panic!("Username muito longo! Máximo 16 caracteres.");
+impl VarCodec for HostAddress {
+    fn write_var(&self, buf: &mut Vec<u8>) {
+        LayoutWriter::new()
+            .u16(self.port)
+            .string(&self.host)
+            .finish(buf);
     }
-    let username_var_size = varint_size(username_bytes.len() as u32) + username_bytes.len();
-
-    // Offset 82-85: languageOffset (-1 = null)
-    payload.extend_from_slice(&(-1i32).to_le_bytes());
 
-    // Offset 86-89: identityTokenOffset
-    let identity_token_offset: i32;
-    let token_bytes: &[u8];
-    if let Some(token) = identity_token {
-        // identityToken vem DEPOIS do username no variable block
-        identity_token_offset = current_offset + username_var_size as i32;
-        token_bytes = token.as_bytes();
-    } else {
-        identity_token_offset = -1;
-        token_bytes = &[];
+    fn read_var(data: &[u8]) -> Option<(Self, usize)> {
+        let mut r = LayoutReader::new(data, 0, 1);
+        let port = r.u16()?;
+        let host = r.string()?;
+        Some((Self { host, port }, r.end()))
     }
-    payload.extend_from_slice(&identity_token_offset.to_le_bytes());
+}
 
-    // Offset 90-93: usernameOffset (0 = início do variable block)
-    payload.extend_from_slice(&current_offset.to_le_bytes());
+/// Asset referenciado pelo servidor durante o Setup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub hash: String,
+    pub name: String,
+}
 
-    // Offset 94-97: referralDataOffset (-1 = null)
-    payload.extend_from_slice(&(-1i32).to_le_bytes());
+impl VarCodec for Asset {
+    fn write_var(&self, buf: &mut Vec<u8>) {
+        LayoutWriter::new()
+            .fixed_ascii(&self.hash, 64)
+            .string(&self.name)
+            .finish(buf);
+    }
 
-    // Offset 98-101: referralSourceOffset (-1 = null)
-    payload.extend_from_slice(&(-1i32).to_le_bytes());
+    fn read_var(data: &[u8]) -> Option<(Self, usize)> {
+        let mut r = LayoutReader::new(data, 0, 1);
+        let hash = r.fixed_ascii(64)?;
+        let name = r.string()?;
+        Some((Self { hash, name }, r.end()))
+    }
+}
 
-    // ========== VARIABLE BLOCK (inicia offset 102) ==========
+// ============================================================================
+// Pacotes gerados a partir da descrição do protocolo
+// ============================================================================
+
+include!(concat!(env!("OUT_DIR"), "/packet_structs.rs"));
+
+/// Tamanho máximo de username aceito pelo servidor
+pub const MAX_USERNAME_LENGTH: usize = 16;
+
+impl ConnectPacket {
+    pub fn new(username: &str, uuid: Uuid, identity_token: Option<&str>) -> Self {
+        Self {
+            protocol_hash: String::from_utf8_lossy(PROTOCOL_HASH).into_owned(),
+            client_type: ClientType::Game,
+            uuid,
+            language: None,
+            identity_token: identity_token.map(str::to_string),
+            username: username.to_string(),
+            referral_data: None,
+            referral_source: None,
+        }
+    }
+}
 
-    // Username como VarString (VarInt length + bytes)
-    write_varint(&mut payload, username_bytes.len() as u32);
-    payload.extend_from_slice(username_bytes);
+// ============================================================================
+// Disconnect (1)
+// ============================================================================
+
+/// Categoria do motivo de um Disconnect, deduzida do texto enviado pelo servidor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectKind {
+    Banned,
+    ServerFull,
+    /// Falha na autenticação mútua, tokens ou sessão (ex: "Server authentication failed")
+    AuthFailed,
+    /// Protocolo incompatível, vale tentar outra versão conhecida
+    OutdatedClient,
+    Other,
+}
 
-    // IdentityToken como VarString (se presente)
-    if identity_token.is_some() {
-        write_varint(&mut payload, token_bytes.len() as u32);
-        payload.extend_from_slice(token_bytes);
+impl DisconnectKind {
+    pub fn classify(reason: &str) -> Self {
+        let reason = reason.to_lowercase();
+        let matches = |needles: &[&str]| needles.iter().any(|needle| reason.contains(needle));
+
+        if matches(&["banned", "blacklisted"]) {
+            DisconnectKind::Banned
+        } else if matches(&["server is full", "server full", "too many players"]) {
+            DisconnectKind::ServerFull
+        // Vem antes de OutdatedClient: "Mutual authentication required - please update your client"
+        // é o AuthToken sem grant para o servidor, trocar de versão não resolve
+        } else if matches(&["authentication", "auth failed", "invalid token", "session"]) {
+            DisconnectKind::AuthFailed
+        } else if matches(&["outdated", "incompatible", "protocol version", "protocol hash"]) {
+            DisconnectKind::OutdatedClient
+        } else {
+            DisconnectKind::Other
+        }
     }
+}
 
-    // ========== FRAME HEADER ==========
-    // Conforme PacketIO.java:
-    // [Payload Length (u32 LE)] [Packet ID (u32 LE)] [Payload]
-    // O Length é APENAS o tamanho do payload (não inclui o ID).
+impl DisconnectPacket {
+    pub fn new(reason: &str) -> Self {
+        Self {
+            disconnect_type: DisconnectType::Disconnect,
+            reason: Some(reason.to_string()),
+        }
+    }
 
-    let mut frame = Vec::with_capacity(8 + payload.len());
+    /// Texto do motivo, vazio se o servidor não mandou nenhum
+    pub fn reason_text(&self) -> &str {
+        self.reason.as_deref().unwrap_or_default()
+    }
 
-    // Payload length (4 bytes LE)
-    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    pub fn kind(&self) -> DisconnectKind {
+        DisconnectKind::classify(self.reason_text())
+    }
+}
 
-    // Packet ID (4 bytes LE) - Connect = 0
-    frame.extend_from_slice(&PACKET_CONNECT.to_le_bytes());
+// ============================================================================
+// Ping (2)
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PingPacket {
+    pub id: u32,
+    pub time: Option<InstantData>,
+    pub last_ping_value_raw: i32,
+    pub last_ping_value_direct: i32,
+    pub last_ping_value_tick: i32,
+}
 
-    // Payload
-    frame.extend(payload);
+impl Packet for PingPacket {
+    const ID: u32 = PACKET_PING;
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        LayoutWriter::new()
+            .u32(self.id)
+            .opt_fixed(self.time.as_ref())
+            .i32(self.last_ping_value_raw)
+            .i32(self.last_ping_value_direct)
+            .i32(self.last_ping_value_tick)
+            .finish(buf);
+    }
 
-    frame
+    // Decode manual: os últimos valores de ping são lidos só se presentes
+    fn decode(data: &[u8]) -> Option<Self> {
+        let mut r = LayoutReader::new(data, 1, 0);
+        Some(Self {
+            id: r.u32()?,
+            time: r.opt_fixed()?,
+            last_ping_value_raw: r.i32().unwrap_or(0),
+            last_ping_value_direct: r.i32().unwrap_or(0),
+            last_ping_value_tick: r.i32().unwrap_or(0),
+        })
+    }
 }
 
-pub fn build_auth_token(access_token: Option<&str>, authorization_grant: Option<&str>) -> Vec<u8> {
-    let mut payload = Vec::new();
+// ============================================================================
+// PasswordResponse (15)
+// ============================================================================
 
-    let mut null_bits: u8 = 0;
-    if access_token.is_some() { null_bits |= 1; }
-    if authorization_grant.is_some() { null_bits |= 2; }
+impl PasswordResponsePacket {
+    /// Resposta ao desafio de senha do servidor
+    pub fn new(challenge: &[u8], password: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(challenge);
+        hasher.update(password.as_bytes());
 
-    payload.push(null_bits);
+        Self { hash: Some(hasher.finalize().to_vec()) }
+    }
+}
 
-    // Offsets (8 bytes - 2 ints)
-    let mut current_offset: i32 = 0;
+// ============================================================================
+// PlayerOptions (33)
+// ============================================================================
+
+/// PlayerOptions sem skin (o servidor usa a do perfil)
+///
+/// A skin é uma estrutura grande que o bot não precisa customizar, então é
+/// mantida como bytes já serializados.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerOptionsPacket {
+    pub skin: Option<Vec<u8>>,
+}
 
-    // Access Token Offset
-    let access_token_bytes = access_token.unwrap_or("").as_bytes();
-    let access_token_start = if access_token.is_some() {
-        let start = current_offset;
-        // VarInt size + String bytes
-        current_offset += varint_size(access_token_bytes.len() as u32) as i32 + access_token_bytes.len() as i32;
-        start
-    } else {
-        -1
-    };
-    payload.extend_from_slice(&access_token_start.to_le_bytes());
-
-    // Grant Offset
-    let grant_bytes = authorization_grant.unwrap_or("").as_bytes();
-    let grant_start = if authorization_grant.is_some() {
-        current_offset // Grant starts right after accessToken
-    } else {
-        -1
-    };
-    payload.extend_from_slice(&grant_start.to_le_bytes());
+impl Packet for PlayerOptionsPacket {
+    const ID: u32 = PACKET_PLAYER_OPTIONS;
 
-    // Variable Block
-    if let Some(token) = access_token {
-        write_varint(&mut payload, token.as_bytes().len() as u32);
-        payload.extend_from_slice(token.as_bytes());
+    fn encode(&self, buf: &mut Vec<u8>) {
+        LayoutWriter::new()
+            .opt_raw(self.skin.clone())
+            .finish(buf);
     }
 
-    if let Some(grant) = authorization_grant {
-        write_varint(&mut payload, grant.as_bytes().len() as u32);
-        payload.extend_from_slice(grant.as_bytes());
+    fn decode(data: &[u8]) -> Option<Self> {
+        let mut r = LayoutReader::new(data, 1, 1);
+        Some(Self {
+            skin: r.opt_raw(|rest| Some((rest.to_vec(), rest.len())))?,
+        })
     }
+}
 
-    // Frame (Standard Hytale/PacketIO)
-    let mut frame = Vec::with_capacity(8 + payload.len());
-    // Length (u32 LE)
-    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
-    // ID (u32 LE)
-    frame.extend_from_slice(&PACKET_AUTH_TOKEN.to_le_bytes());
-    // Payload
-    frame.extend(payload);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    frame
-}
+    fn roundtrip<P: Packet + PartialEq + std::fmt::Debug>(packet: P) {
+        let mut payload = Vec::new();
+        packet.encode(&mut payload);
+        assert_eq!(P::decode(&payload), Some(packet));
+    }
 
+    fn test_uuid() -> Uuid {
+        Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef)
+    }
 
-/// Constrói um pacote Pong em resposta a um Ping
-pub fn build_pong_packet(ping_id: u32, ping_time_seconds: i64, ping_time_nanos: i32) -> Vec<u8> {
-    let mut payload = Vec::with_capacity(32);
+    #[test]
+    fn connect_roundtrip() {
+        roundtrip(ConnectPacket::new("bot", test_uuid(), Some("identity")));
+        roundtrip(ConnectPacket {
+            language: Some("pt_BR".to_string()),
+            referral_data: Some(vec![1, 2, 3]),
+            referral_source: Some(HostAddress { host: "play.exemplo.com".to_string(), port: 5520 }),
+            ..ConnectPacket::new("bot", test_uuid(), None)
+        });
+    }
 
-    // nullBits (1 byte)
-    payload.push(0x00);
+    /// Layout do builder escrito à mão que veio antes das structs geradas: fixed
+    /// block de 102 bytes com os 5 offsets em 82..102
+    ///
+    /// O builder antigo escrevia o username antes do token no variable block; o
+    /// gerado segue a ordem de declaração, e os offsets apontam para cada um.
+    #[test]
+    fn connect_matches_handwritten_layout() {
+        let uuid = test_uuid();
+        let mut expected = vec![0x02];
+        expected.extend_from_slice(PROTOCOL_HASH);
+        expected.push(ClientType::Game as u8);
+        expected.extend_from_slice(uuid.as_bytes());
+        // language, identity_token, username, referral_data, referral_source
+        for offset in [-1i32, 0, 4, -1, -1] {
+            expected.extend_from_slice(&offset.to_le_bytes());
+        }
+        expected.extend_from_slice(b"\x03tok\x03bot");
+
+        let mut payload = Vec::new();
+        ConnectPacket::new("bot", uuid, Some("tok")).encode(&mut payload);
+        assert_eq!(payload.len(), 102 + 8);
+        assert_eq!(payload, expected);
+    }
 
-    // id (4 bytes LE) - mesmo ID do ping
-    payload.extend_from_slice(&ping_id.to_le_bytes());
+    #[test]
+    fn disconnect_kind_classification() {
+        let cases = [
+            ("You are banned from this server", DisconnectKind::Banned),
+            ("Server is full", DisconnectKind::ServerFull),
+            ("Too many players online", DisconnectKind::ServerFull),
+            ("Mutual authentication required - please update your client", DisconnectKind::AuthFailed),
+            ("Incompatible protocol hash", DisconnectKind::OutdatedClient),
+            ("Outdated client!", DisconnectKind::OutdatedClient),
+            ("Server authentication failed", DisconnectKind::AuthFailed),
+            ("Invalid token", DisconnectKind::AuthFailed),
+            ("Session expired", DisconnectKind::AuthFailed),
+            ("Kicked by an operator", DisconnectKind::Other),
+            ("", DisconnectKind::Other),
+        ];
+        for (reason, kind) in cases {
+            assert_eq!(DisconnectKind::classify(reason), kind, "{:?}", reason);
+        }
+    }
 
-    // time - InstantData (12 bytes)
-    payload.extend_from_slice(&ping_time_seconds.to_le_bytes()); // 8 bytes
-    payload.extend_from_slice(&ping_time_nanos.to_le_bytes());   // 4 bytes
+    #[test]
+    fn disconnect_roundtrip() {
+        roundtrip(DisconnectPacket::new("Até mais"));
+        roundtrip(DisconnectPacket { disconnect_type: DisconnectType::Crash, reason: None });
+    }
 
-    // type - PongType (1 byte): Raw=0, Direct=1, Tick=2
-    payload.push(0); // Raw
+    #[test]
+    fn ping_pong_roundtrip() {
+        let time = Some(InstantData { seconds: 1_768_000_000, nanos: 123_456 });
+        roundtrip(PingPacket {
+            id: 7,
+            time,
+            last_ping_value_raw: 10,
+            last_ping_value_direct: 20,
+            last_ping_value_tick: 30,
+        });
+        roundtrip(PongPacket { id: 7, time, pong_type: PongType::Tick, packet_queue_size: 3 });
+        roundtrip(PongPacket { id: 8, time: None, pong_type: PongType::Raw, packet_queue_size: 0 });
+    }
 
-    // packetQueueSize (2 bytes LE)
-    payload.extend_from_slice(&0u16.to_le_bytes());
+    #[test]
+    fn auth_packets_roundtrip() {
+        roundtrip(AuthGrantPacket {
+            authorization_grant: Some("grant".to_string()),
+            server_identity_token: Some("server-identity".to_string()),
+        });
+        roundtrip(AuthGrantPacket { authorization_grant: Some("grant".to_string()), server_identity_token: None });
+        roundtrip(AuthTokenPacket {
+            access_token: Some("access".to_string()),
+            server_authorization_grant: None,
+        });
+        roundtrip(ServerAuthTokenPacket {
+            server_access_token: Some("server-access".to_string()),
+            password_challenge: Some(vec![9; 32]),
+        });
+        roundtrip(ServerAuthTokenPacket { server_access_token: None, password_challenge: None });
+    }
 
-    // Frame
-    let mut frame = Vec::with_capacity(8 + payload.len());
-    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
-    frame.extend_from_slice(&PACKET_PONG.to_le_bytes());
-    frame.extend(payload);
+    #[test]
+    fn password_packets_roundtrip() {
+        roundtrip(ConnectAcceptPacket { password_challenge: Some(vec![1, 2, 3, 4]) });
+        roundtrip(ConnectAcceptPacket { password_challenge: None });
+        roundtrip(PasswordResponsePacket::new(&[1, 2, 3, 4], "senha"));
+    }
 
-    frame
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let mut payload = Vec::new();
+        ConnectPacket::new("bot", test_uuid(), Some("tok")).encode(&mut payload);
+        for len in [0, 1, 101, payload.len() - 1] {
+            assert_eq!(ConnectPacket::decode(&payload[..len]), None, "len {}", len);
+        }
+    }
 }
@@ -0,0 +1,48 @@
+//! Setup phase handlers
+
+use tracing::info;
+use super::packets::*;
+use super::keepalive::PacketSender;
+use super::constants::*;
+use super::error::ProtocolError;
+
+// ============================================================================
+// Handlers de Pacotes Individuais
+// ============================================================================
+
+pub(super) fn handle_world_settings(out: &PacketSender, payload: &[u8]) -> Result<(), ProtocolError> {
+    let settings = WorldSettingsPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_WORLD_SETTINGS })?;
+
+    let asset_count = settings.required_assets.as_ref().map_or(0, |a| a.len());
+    info!(
+        "🌍 WorldSettings recebido (altura: {}, assets requeridos: {})",
+        settings.world_height, asset_count
+    );
+
+    // O bot não precisa de assets, pede nenhum
+    out.send(&RequestAssetsPacket { assets: None })?;
+
+    Ok(())
+}
+
+pub(super) fn handle_world_load_finished(out: &PacketSender) -> Result<(), ProtocolError> {
+    info!("📦 Carregamento do mundo concluído, enviando PlayerOptions...");
+    out.send(&PlayerOptionsPacket { skin: None })?;
+
+    Ok(())
+}
+
+pub(super) fn handle_join_world(out: &PacketSender, payload: &[u8]) -> Result<JoinWorldPacket, ProtocolError> {
+    let join = JoinWorldPacket::decode(payload)
+        .ok_or(ProtocolError::Malformed { packet_id: PACKET_JOIN_WORLD })?;
+
+    info!("🚪 JoinWorld recebido (mundo: {}), enviando ClientReady...", join.world_uuid);
+    let ready = ClientReadyPacket {
+        ready_for_chunks: true,
+        ready_for_gameplay: true,
+    };
+    out.send(&ready)?;
+
+    Ok(join)
+}
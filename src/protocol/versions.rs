@@ -0,0 +1,109 @@
+//! Protocol version registry keyed by protocol hash
+
+use super::constants::get_packet_name;
+use super::packets::Packet;
+use super::codec::{compress_payload, frame_packet};
+use super::error::ProtocolError;
+
+// ============================================================================
+// Versões
+// ============================================================================
+
+/// Um pacote na tabela de IDs de uma versão
+#[derive(Debug)]
+pub struct PacketEntry {
+    pub id: u32,
+    pub name: &'static str,
+    /// Layout igual ao da versão atual, então as structs geradas servem de codec
+    pub compatible: bool,
+    /// Payload trafega comprimido com zstd
+    pub compressed: bool,
+}
+
+/// Uma versão do protocolo: hash enviado no Connect e tabela de pacotes
+#[derive(Debug)]
+pub struct ProtocolVersion {
+    pub version: &'static str,
+    pub hash: &'static str,
+    pub packets: &'static [PacketEntry],
+}
+
+// VERSIONS e uma static por versão, geradas pelo build.rs a partir de protocol/
+include!(concat!(env!("OUT_DIR"), "/versions.rs"));
+
+impl ProtocolVersion {
+    /// Nome do pacote com este ID nesta versão
+    pub fn packet_name(&self, wire_id: u32) -> &'static str {
+        self.packets.iter()
+            .find(|p| p.id == wire_id)
+            .map_or("Unknown", |p| p.name)
+    }
+
+    /// Indica se o pacote com este ID nesta versão chega comprimido
+    pub fn is_compressed(&self, wire_id: u32) -> bool {
+        self.packets.iter().any(|p| p.id == wire_id && p.compressed)
+    }
+
+    /// Converte um Packet ID da versão atual para o ID usado por esta versão
+    pub fn to_wire(&self, packet_id: u32) -> Option<u32> {
+        self.entry_by_name(get_packet_name(packet_id)).map(|p| p.id)
+    }
+
+    /// Converte um ID recebido nesta versão para o Packet ID da versão atual
+    ///
+    /// Retorna None para pacotes que a versão atual não conhece.
+    pub fn from_wire(&self, wire_id: u32) -> Option<u32> {
+        let name = self.packets.iter().find(|p| p.id == wire_id)?.name;
+        current().entry_by_name(name).map(|p| p.id)
+    }
+
+    /// Indica se as structs do bot sabem codificar este pacote nesta versão
+    pub fn supports(&self, packet_id: u32) -> bool {
+        self.entry_by_name(get_packet_name(packet_id)).is_some_and(|p| p.compatible)
+    }
+
+    /// Codifica o pacote com o header [Length] [ID] usando o ID desta versão
+    pub fn frame<P: Packet>(&self, packet: &P) -> Result<Vec<u8>, ProtocolError> {
+        let wire_id = self.to_wire(P::ID)
+            .filter(|_| self.supports(P::ID))
+            .ok_or(ProtocolError::UnsupportedPacket { packet_id: P::ID, version: self.version })?;
+
+        let mut payload = Vec::new();
+        packet.encode(&mut payload);
+        if self.is_compressed(wire_id) {
+            payload = compress_payload(&payload)
+                .map_err(|source| ProtocolError::Compress { packet_id: P::ID, source })?;
+        }
+        Ok(frame_packet(wire_id, payload))
+    }
+
+    fn entry_by_name(&self, name: &str) -> Option<&PacketEntry> {
+        self.packets.iter().find(|p| p.name == name)
+    }
+}
+
+// ============================================================================
+// Registro
+// ============================================================================
+
+/// Versão mais nova conhecida, a mesma das constantes em `constants`
+pub fn current() -> &'static ProtocolVersion {
+    VERSIONS[0]
+}
+
+/// Procura uma versão pelo nome (ex: "2026.01.13") ou pelo protocol hash
+pub fn find_version(version_or_hash: &str) -> Option<&'static ProtocolVersion> {
+    VERSIONS.iter()
+        .copied()
+        .find(|v| v.version == version_or_hash || v.hash.eq_ignore_ascii_case(version_or_hash))
+}
+
+/// Versões a tentar numa conexão: só a escolhida, ou todas da mais nova para a mais antiga
+pub fn candidate_versions(version_or_hash: Option<&str>) -> Result<Vec<&'static ProtocolVersion>, ProtocolError> {
+    match version_or_hash {
+        Some(wanted) => find_version(wanted)
+            .map(|v| vec![v])
+            .ok_or_else(|| ProtocolError::UnknownVersion(wanted.to_string())),
+        None => Ok(VERSIONS.to_vec()),
+    }
+}
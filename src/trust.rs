@@ -0,0 +1,210 @@
+//! Server certificate trust: trust-on-first-use, pinning and the insecure opt-in
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use thiserror::Error;
+use tracing::{error, info, warn};
+use crate::accounts::{config_root, write_private};
+use crate::utils::{fingerprints_match, x509_fingerprint};
+
+const KNOWN_SERVERS_FILE: &str = "known_servers.json";
+
+// ============================================================================
+// Política
+// ============================================================================
+
+/// Como decidir se o certificado apresentado pelo servidor é confiável
+///
+/// Os servidores Hytale usam certificados self-signed, então não há CA para validar:
+/// o que se compara é o fingerprint `x5t#S256` do certificado.
+#[derive(Debug, Clone)]
+pub enum ServerTrust {
+    /// Aceita o certificado na primeira conexão e exige o mesmo nas seguintes
+    TrustOnFirstUse(Arc<KnownServers>),
+    /// Só aceita o certificado com este fingerprint
+    Pinned(String),
+    /// Aceita qualquer certificado: qualquer um no caminho pode se passar pelo servidor
+    DangerousAcceptAny,
+}
+
+/// Certificado recusado pela política de confiança
+#[derive(Debug, Error)]
+pub enum TrustError {
+    #[error("Certificado de {server} mudou! Esperado {expected}, recebido {actual}")]
+    Changed { server: String, expected: String, actual: String },
+
+    #[error("Certificado de {server} não bate com o fixado: esperado {expected}, recebido {actual}")]
+    PinMismatch { server: String, expected: String, actual: String },
+}
+
+// ============================================================================
+// Servidores conhecidos (TOFU)
+// ============================================================================
+
+/// Fingerprint do certificado de cada servidor já visto, salvo em JSON
+///
+/// A chave é o endereço como foi configurado (ex: `play.exemplo.com:5520`).
+#[derive(Debug)]
+pub struct KnownServers {
+    path: PathBuf,
+    servers: Mutex<BTreeMap<String, String>>,
+}
+
+impl KnownServers {
+    /// `<config do usuário>/hytale-bot/known_servers.json`
+    pub fn default_path() -> PathBuf {
+        Self::path_in(&config_root())
+    }
+
+    /// Arquivo dentro de outra pasta de configuração
+    pub fn path_in(config_dir: &Path) -> PathBuf {
+        config_dir.join(KNOWN_SERVERS_FILE)
+    }
+
+    /// Lê o arquivo, vazio se ele ainda não existe
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+
+        let servers = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self { path, servers: Mutex::new(servers) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Fingerprint registrado para o servidor
+    pub fn get(&self, server: &str) -> Option<String> {
+        self.servers.lock().unwrap().get(server).cloned()
+    }
+
+    /// Registra (ou troca) o fingerprint do servidor e salva o arquivo
+    pub fn remember(&self, server: &str, fingerprint: &str) -> io::Result<()> {
+        let mut servers = self.servers.lock().unwrap();
+        servers.insert(server.to_string(), fingerprint.to_string());
+        self.save(&servers)
+    }
+
+    /// Esquece o servidor, para aceitar um certificado novo de propósito
+    pub fn forget(&self, server: &str) -> io::Result<bool> {
+        let mut servers = self.servers.lock().unwrap();
+        if servers.remove(server).is_none() {
+            return Ok(false);
+        }
+        self.save(&servers)?;
+        Ok(true)
+    }
+
+    fn save(&self, servers: &BTreeMap<String, String>) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_vec_pretty(servers).map_err(io::Error::other)?;
+        write_private(&self.path, &contents)
+    }
+}
+
+// ============================================================================
+// Verificador
+// ============================================================================
+
+/// Verificador rustls que aplica a `ServerTrust` a um servidor
+///
+/// A assinatura do handshake é sempre checada (menos no `DangerousAcceptAny`): sem
+/// isso um intermediário poderia reapresentar o certificado conhecido sem ter a chave.
+#[derive(Debug)]
+pub(crate) struct TrustVerifier {
+    server: String,
+    trust: ServerTrust,
+    provider: Arc<CryptoProvider>,
+}
+
+impl TrustVerifier {
+    pub(crate) fn new(server: &str, trust: ServerTrust, provider: Arc<CryptoProvider>) -> Arc<Self> {
+        Arc::new(Self { server: server.to_string(), trust, provider })
+    }
+
+    fn check(&self, fingerprint: String) -> Result<(), TrustError> {
+        match &self.trust {
+            ServerTrust::DangerousAcceptAny => Ok(()),
+
+            ServerTrust::Pinned(expected) if fingerprints_match(expected, &fingerprint) => Ok(()),
+            ServerTrust::Pinned(expected) => Err(TrustError::PinMismatch {
+                server: self.server.clone(),
+                expected: expected.clone(),
+                actual: fingerprint,
+            }),
+
+            ServerTrust::TrustOnFirstUse(known) => match known.get(&self.server) {
+                Some(expected) if fingerprints_match(&expected, &fingerprint) => Ok(()),
+                Some(expected) => Err(TrustError::Changed { server: self.server.clone(), expected, actual: fingerprint }),
+                None => {
+                    info!("🤝 Primeira conexão com {}, certificado registrado ({})", self.server, fingerprint);
+                    // Salvar falhar não muda a decisão desta conexão, só a da próxima
+                    if let Err(e) = known.remember(&self.server, &fingerprint) {
+                        warn!("⚠️ Falha ao salvar {}: {}", known.path().display(), e);
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+impl ServerCertVerifier for TrustVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.check(x509_fingerprint(end_entity)).map_err(|e| {
+            error!("⛔ {}", e);
+            rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(Arc::new(e))))
+        })?;
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        if let ServerTrust::DangerousAcceptAny = self.trust {
+            return Ok(HandshakeSignatureValid::assertion());
+        }
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        if let ServerTrust::DangerousAcceptAny = self.trust {
+            return Ok(HandshakeSignatureValid::assertion());
+        }
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,16 @@
+//! X.509 certificate fingerprints for token binding
+
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+
+/// Fingerprint `x5t#S256` do certificado: SHA-256 do DER em base64url sem padding
+///
+/// É o formato enviado ao session service e o que volta no claim `cnf` do access token.
+pub fn x509_fingerprint(cert_der: &[u8]) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(cert_der))
+}
+
+/// Compara dois fingerprints ignorando o padding `=` que algum lado possa ter mantido
+pub fn fingerprints_match(a: &str, b: &str) -> bool {
+    a.trim_end_matches('=') == b.trim_end_matches('=')
+}
@@ -1,26 +1,45 @@
 //! JWT parsing utilities
 
 use base64::{engine::general_purpose, Engine as _};
+use tracing::{debug, warn};
 
 /// Extrai o "sub" (UUID) de um JWT sem validar a assinatura
 pub fn extract_jwt_subject(jwt: &str) -> Option<String> {
     let parts: Vec<&str> = jwt.split('.').collect();
     if parts.len() < 2 {
-        println!("⚠️ JWT inválido - menos de 2 partes");
+        warn!("⚠️ JWT inválido - menos de 2 partes");
         return None;
     }
 
     let payload_bytes = decode_jwt_payload(parts[1])?;
     let payload_str = String::from_utf8(payload_bytes).ok()?;
 
-    println!("📄 JWT payload: {}", &payload_str[..payload_str.len().min(200)]);
+    debug!("📄 JWT payload: {}", &payload_str[..payload_str.len().min(200)]);
 
     let json: serde_json::Value = serde_json::from_str(&payload_str).ok()?;
     let sub = json.get("sub").and_then(|v| v.as_str()).map(|s| s.to_string());
-    println!("🔑 Servidor UUID extraído: {:?}", sub);
+    debug!("🔑 Servidor UUID extraído: {:?}", sub);
     sub
 }
 
+/// Extrai o "exp" (Unix timestamp em segundos) de um JWT sem validar a assinatura
+pub fn extract_jwt_expiry(jwt: &str) -> Option<u64> {
+    let payload_b64 = jwt.split('.').nth(1)?;
+    let payload_bytes = decode_jwt_payload(payload_b64)?;
+
+    let json: serde_json::Value = serde_json::from_slice(&payload_bytes).ok()?;
+    json.get("exp").and_then(|v| v.as_u64())
+}
+
+/// Extrai o fingerprint `cnf.x5t#S256` ao qual o token está preso, sem validar a assinatura
+pub fn extract_jwt_cert_thumbprint(jwt: &str) -> Option<String> {
+    let payload_b64 = jwt.split('.').nth(1)?;
+    let payload_bytes = decode_jwt_payload(payload_b64)?;
+
+    let json: serde_json::Value = serde_json::from_slice(&payload_bytes).ok()?;
+    json.pointer("/cnf/x5t#S256").and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
 fn decode_jwt_payload(payload_b64: &str) -> Option<Vec<u8>> {
     general_purpose::URL_SAFE_NO_PAD.decode(payload_b64)
         .or_else(|_| general_purpose::URL_SAFE.decode(payload_b64))
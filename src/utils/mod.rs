@@ -1,5 +1,11 @@
 pub mod jwt;
 pub mod debug;
+pub mod fingerprint;
+pub mod time;
+pub mod random;
 
 pub use jwt::*;
 pub use debug::*;
+pub use fingerprint::*;
+pub use time::*;
+pub use random::*;
@@ -0,0 +1,8 @@
+//! Random identifiers
+
+use uuid::Uuid;
+
+/// UUID v4 com bytes do `rand`, sem depender da feature `v4` do crate uuid
+pub fn random_uuid() -> Uuid {
+    uuid::Builder::from_random_bytes(rand::random()).into_uuid()
+}
@@ -0,0 +1,10 @@
+//! Time helpers
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Unix timestamp atual em segundos, o mesmo formato do "exp" dos JWTs
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,158 @@
+//! Login, sessão de jogo e troca de grants contra o session service falso
+
+mod common;
+
+use std::time::Duration;
+use hytale_bot::accounts::AccountStore;
+use hytale_bot::auth::{self, AuthClient, AuthError, CachedTokens, GameSession, LoginMode, TokenCache};
+use hytale_bot::identity::ClientIdentity;
+use hytale_bot::mock::{MockSessionConfig, MockSessionService};
+use hytale_bot::utils::{extract_jwt_cert_thumbprint, random_uuid, unix_now};
+use common::{game_session, login, TempDir};
+
+#[tokio::test]
+async fn login_with_code_file_stores_tokens() {
+    let mock = MockSessionService::start().await.unwrap();
+    let dir = TempDir::new();
+    let cache = TokenCache::new(AccountStore::new(&*dir));
+
+    let response = login(&mock, &cache).await;
+    assert!(response.refresh_token.is_some());
+
+    let cached = cache.load("bot").unwrap().expect("tokens não salvos");
+    assert_eq!(cached.access_token, response.access_token);
+    assert!(cached.is_valid());
+
+    // Com o token válido no cache o código não é pedido de novo
+    let client = AuthClient::new(mock.endpoints());
+    let again = tokio::time::timeout(
+        Duration::from_secs(5),
+        auth::login(&client, &cache, "bot", &LoginMode::File(dir.join("nunca-criado"))),
+    )
+    .await
+    .expect("login pediu o código com token em cache")
+    .unwrap();
+    assert_eq!(again.access_token, response.access_token);
+}
+
+#[tokio::test]
+async fn expired_token_is_refreshed_without_login() {
+    let mock = MockSessionService::start().await.unwrap();
+    let dir = TempDir::new();
+    let cache = TokenCache::new(AccountStore::new(&*dir));
+    let response = login(&mock, &cache).await;
+
+    // tokens.json com o access token vencido e o refresh token ainda bom
+    let expired = CachedTokens {
+        access_token: "vencido".to_string(),
+        refresh_token: response.refresh_token.clone(),
+        expires_at: unix_now().saturating_sub(60),
+        scope: response.scope.clone(),
+        token_type: response.token_type.clone(),
+    };
+    cache.store("bot", &expired).unwrap();
+
+    // Sem o arquivo do código, um login interativo nunca terminaria
+    let client = AuthClient::new(mock.endpoints());
+    let refreshed = tokio::time::timeout(
+        Duration::from_secs(5),
+        auth::login(&client, &cache, "bot", &LoginMode::File(dir.join("nunca-criado"))),
+    )
+    .await
+    .expect("login pediu o código em vez de usar o refresh token")
+    .unwrap();
+    assert_ne!(refreshed.access_token, "vencido");
+
+    let cached = cache.load("bot").unwrap().expect("tokens não salvos");
+    assert_eq!(cached.access_token, refreshed.access_token);
+    assert!(cached.is_valid());
+    assert!(cached.refresh_token.is_some());
+}
+
+#[tokio::test]
+async fn import_refresh_token_stores_account() {
+    let mock = MockSessionService::start().await.unwrap();
+    let dir = TempDir::new();
+    let cache = TokenCache::new(AccountStore::new(&*dir));
+    let refresh_token = login(&mock, &cache).await.refresh_token.unwrap();
+
+    let client = AuthClient::new(mock.endpoints());
+    auth::import_refresh_token(&client, &cache, "outra", &refresh_token).await.unwrap();
+    assert!(cache.load("outra").unwrap().is_some_and(|t| t.is_valid()));
+
+    let invalid = auth::import_refresh_token(&client, &cache, "invalida", "nao-e-um-token").await;
+    assert!(invalid.is_err());
+    assert!(cache.load("invalida").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn game_session_create_refresh_and_delete() {
+    let mock = MockSessionService::start().await.unwrap();
+    let dir = TempDir::new();
+    let cache = TokenCache::new(AccountStore::new(&*dir));
+    let access_token = login(&mock, &cache).await.access_token;
+
+    let client = AuthClient::new(mock.endpoints());
+    let session = GameSession::create(&client, &access_token, random_uuid()).await.unwrap();
+    let first = session.tokens();
+    assert!(first.expires_at.is_some());
+    assert!(!session.needs_refresh());
+
+    // O refresh troca os tokens e invalida o session token antigo
+    session.refresh().await.unwrap();
+    let second = session.tokens();
+    assert_ne!(first.session_token, second.session_token);
+    assert!(auth::refresh_game_session(&client, &first.session_token).await.is_err());
+
+    session.delete().await.unwrap();
+    assert!(session.refresh().await.is_err());
+}
+
+#[tokio::test]
+async fn game_session_rejects_invalid_access_token() {
+    let mock = MockSessionService::start().await.unwrap();
+    let client = AuthClient::new(mock.endpoints());
+
+    assert!(GameSession::create(&client, "nao-e-um-token", random_uuid()).await.is_err());
+}
+
+/// Grant para um servidor qualquer e a troca por um access token preso a `identity`
+async fn exchange(mock: &MockSessionService, identity: &ClientIdentity) -> Result<String, AuthError> {
+    let session = game_session(mock, random_uuid()).await;
+    let client = session.client();
+    let tokens = session.tokens();
+
+    let grant = auth::request_server_auth_grant(client, &tokens.identity_token, "servidor", &tokens.session_token)
+        .await
+        .unwrap();
+    auth::exchange_grant_for_access_token(client, &grant, &tokens.session_token, identity.fingerprint()).await
+}
+
+#[tokio::test]
+async fn access_token_is_bound_to_client_certificate() {
+    let mock = MockSessionService::start().await.unwrap();
+    let identity = ClientIdentity::generate().unwrap();
+
+    let access_token = exchange(&mock, &identity).await.unwrap();
+    assert_eq!(extract_jwt_cert_thumbprint(&access_token).as_deref(), Some(identity.fingerprint()));
+}
+
+#[tokio::test]
+async fn access_token_bound_to_other_certificate_is_rejected() {
+    let other = ClientIdentity::generate().unwrap();
+    let mock = MockSessionService::start_with(MockSessionConfig {
+        bound_fingerprint: Some(other.fingerprint().to_string()),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let identity = ClientIdentity::generate().unwrap();
+
+    match exchange(&mock, &identity).await {
+        Err(AuthError::CertificateBinding { expected, actual }) => {
+            assert_eq!(expected, identity.fingerprint());
+            assert_eq!(actual.as_deref(), Some(other.fingerprint()));
+        }
+        other => panic!("esperava CertificateBinding, veio {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,45 @@
+//! Binário `hytale-bot` contra o servidor falso
+
+mod common;
+
+use std::process::Output;
+use std::time::Duration;
+use hytale_bot::mock::{MockGameServer, MockServerConfig};
+use tokio::process::Command;
+use common::TempDir;
+
+/// `hytale-bot ping` com o certificado do mock fixado
+async fn ping(server: &MockGameServer) -> Output {
+    let config_dir = TempDir::new();
+    let command = Command::new(env!("CARGO_BIN_EXE_hytale-bot"))
+        .arg("--config-dir")
+        .arg(&*config_dir)
+        .args(["ping", &server.addr().to_string(), "--server-fingerprint", server.fingerprint()])
+        .kill_on_drop(true)
+        .output();
+
+    tokio::time::timeout(Duration::from_secs(20), command)
+        .await
+        .expect("ping não terminou")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn ping_authenticated_server_succeeds() {
+    let server = MockGameServer::start(MockServerConfig::default()).await.unwrap();
+
+    let output = ping(&server).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Authentication required"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn ping_insecure_server_succeeds() {
+    let server = MockGameServer::start(MockServerConfig { insecure: true, ..Default::default() })
+        .await
+        .unwrap();
+
+    let output = ping(&server).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
@@ -0,0 +1,60 @@
+//! Helpers compartilhados pelos testes de integração
+
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use hytale_bot::accounts::AccountStore;
+use hytale_bot::auth::{self, AuthClient, GameSession, LoginMode, TokenCache};
+use hytale_bot::mock::MockSessionService;
+use hytale_bot::utils::random_uuid;
+use uuid::Uuid;
+
+/// Pasta descartável para contas e tokens, apagada quando sai de escopo
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("hytale-bot-test-{}", random_uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Login com `LoginMode::File` na conta `bot`; o mock aceita qualquer código
+pub async fn login(mock: &MockSessionService, cache: &TokenCache) -> auth::TokenResponse {
+    let client = AuthClient::new(mock.endpoints());
+    let dir = TempDir::new();
+    let code_file = dir.join("code.txt");
+    std::fs::write(&code_file, "qualquer-codigo").unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), auth::login(&client, cache, "bot", &LoginMode::File(code_file)))
+        .await
+        .expect("login não terminou")
+        .unwrap()
+}
+
+/// Login numa conta descartável e uma sessão de jogo para `player_uuid`
+pub async fn game_session(mock: &MockSessionService, player_uuid: Uuid) -> GameSession {
+    let dir = TempDir::new();
+    let cache = TokenCache::new(AccountStore::new(&*dir));
+    let access_token = login(mock, &cache).await.access_token;
+
+    let client = AuthClient::new(mock.endpoints());
+    GameSession::create(&client, &access_token, player_uuid).await.unwrap()
+}
@@ -0,0 +1,125 @@
+//! Layout gerado pelo `#[derive(HytalePacket)]`: nullBits, fixed block e offsets
+
+use hytale_bot::protocol::{HytalePacket, InstantData, Packet};
+
+#[derive(Debug, PartialEq, HytalePacket)]
+#[packet(id = 1000)]
+struct SingleVar {
+    id: u32,
+    #[nullable]
+    #[fixed]
+    time: Option<InstantData>,
+    #[nullable]
+    name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, HytalePacket)]
+#[packet(id = 1001)]
+struct MultipleVars {
+    #[fixed(4)]
+    tag: String,
+    #[nullable]
+    first: Option<String>,
+    second: Vec<u8>,
+    #[nullable]
+    third: Option<String>,
+}
+
+#[derive(Debug, PartialEq, HytalePacket)]
+#[packet(id = 1002)]
+struct NineNullable {
+    #[nullable] a: Option<String>,
+    #[nullable] b: Option<String>,
+    #[nullable] c: Option<String>,
+    #[nullable] d: Option<String>,
+    #[nullable] e: Option<String>,
+    #[nullable] f: Option<String>,
+    #[nullable] g: Option<String>,
+    #[nullable] h: Option<String>,
+    #[nullable] i: Option<String>,
+}
+
+fn encode<P: Packet>(packet: &P) -> Vec<u8> {
+    let mut payload = Vec::new();
+    packet.encode(&mut payload);
+    payload
+}
+
+#[test]
+fn null_bits_follow_declaration_order() {
+    let packet = SingleVar { id: 5, time: None, name: Some("x".to_string()) };
+    let payload = encode(&packet);
+    assert_eq!(payload[0], 0b10);
+    assert_eq!(SingleVar::decode(&payload), Some(packet));
+
+    let packet = SingleVar { id: 5, time: Some(InstantData { seconds: 1, nanos: 2 }), name: None };
+    let payload = encode(&packet);
+    assert_eq!(payload[0], 0b01);
+    assert_eq!(SingleVar::decode(&payload), Some(packet));
+}
+
+#[test]
+fn nullable_fixed_field_keeps_its_space_when_null() {
+    let payload = encode(&SingleVar { id: 5, time: None, name: None });
+    // nullBits + u32 + InstantData zerado, sem nada no variable block
+    assert_eq!(payload.len(), 1 + 4 + 12);
+    assert!(payload[5..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn single_var_field_has_no_offset_table() {
+    let payload = encode(&SingleVar { id: 5, time: None, name: Some("abc".to_string()) });
+    assert_eq!(&payload[1 + 4 + 12..], b"\x03abc");
+}
+
+#[test]
+fn multiple_var_fields_use_offset_table() {
+    let packet = MultipleVars {
+        tag: "ab".to_string(),
+        first: None,
+        second: vec![7, 8],
+        third: Some("z".to_string()),
+    };
+    let payload = encode(&packet);
+
+    let mut expected = vec![0b10];
+    expected.extend_from_slice(b"ab\0\0");
+    for offset in [-1i32, 0, 3] {
+        expected.extend_from_slice(&offset.to_le_bytes());
+    }
+    expected.extend_from_slice(b"\x02\x07\x08");
+    expected.extend_from_slice(b"\x01z");
+    assert_eq!(payload, expected);
+    assert_eq!(MultipleVars::decode(&payload), Some(packet));
+}
+
+#[test]
+fn offset_past_the_buffer_is_rejected() {
+    let mut payload = encode(&MultipleVars {
+        tag: "ab".to_string(),
+        first: None,
+        second: vec![],
+        third: Some("z".to_string()),
+    });
+    // Offset do `third` apontando para fora do buffer
+    payload[1 + 4 + 8..1 + 4 + 12].copy_from_slice(&1000i32.to_le_bytes());
+    assert_eq!(MultipleVars::decode(&payload), None);
+}
+
+#[test]
+fn more_than_eight_nullable_fields_use_two_null_bytes() {
+    let packet = NineNullable {
+        a: Some("a".to_string()),
+        b: None,
+        c: None,
+        d: None,
+        e: None,
+        f: None,
+        g: None,
+        h: None,
+        i: Some("i".to_string()),
+    };
+    let payload = encode(&packet);
+    assert_eq!(&payload[..2], &[0b1, 0b1]);
+    assert_eq!(NineNullable::decode(&payload), Some(packet));
+}
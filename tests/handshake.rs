@@ -0,0 +1,309 @@
+//! Handshake, autenticação, senha e Setup do `Connection` contra o servidor falso
+
+mod common;
+
+use std::time::Duration;
+use hytale_bot::identity::ClientIdentity;
+use hytale_bot::mock::{Action, MockGameServer, MockServerConfig, MockSessionService, ServerEvent, Stage};
+use hytale_bot::net::{self, ServerAddress, TransportOptions};
+use hytale_bot::protocol::{self, versions, Connection, DisconnectKind, Phase, PongType, ProtocolError, PACKET_CONNECT_ACCEPT, PACKET_WORLD_SETTINGS};
+use hytale_bot::trust::ServerTrust;
+use hytale_bot::utils::random_uuid;
+use quinn::Endpoint;
+use common::game_session;
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn insecure() -> MockServerConfig {
+    MockServerConfig { insecure: true, ..Default::default() }
+}
+
+/// Conexão QUIC com o certificado do servidor fixado e o stream do protocolo aberto
+async fn open(server: &MockGameServer, identity: &ClientIdentity) -> (Endpoint, Connection) {
+    let address = ServerAddress::from(server.addr());
+    let trust = ServerTrust::Pinned(server.fingerprint().to_string());
+    let config = net::configure_client(identity, &address, trust, &TransportOptions::default()).unwrap();
+
+    let (endpoint, quic) = net::connect(&config, &address).await.unwrap();
+    let connection = Connection::open(quic, versions::current()).await.unwrap();
+    (endpoint, connection)
+}
+
+/// Connect e fluxo de desenvolvimento até o Setup
+async fn authenticate_insecure(server: &MockGameServer, password: Option<&str>) -> (Endpoint, Connection, Result<(), ProtocolError>) {
+    let identity = ClientIdentity::generate().unwrap();
+    let (endpoint, mut connection) = open(server, &identity).await;
+    if let Some(password) = password {
+        connection.set_password(password);
+    }
+
+    connection.handshake("bot", random_uuid(), None).await.unwrap();
+    let result = connection.authenticate_insecure().await;
+    (endpoint, connection, result)
+}
+
+async fn close(endpoint: Endpoint, mut connection: Connection) {
+    connection.disconnect("Fim do teste").await.unwrap();
+    endpoint.wait_idle().await;
+}
+
+// ============================================================================
+// Caminho feliz
+// ============================================================================
+
+#[tokio::test]
+async fn authenticated_handshake_joins_world() {
+    let mock = MockSessionService::start().await.unwrap();
+    let server = MockGameServer::start(MockServerConfig { authority: Some(mock.authority()), ..Default::default() })
+        .await
+        .unwrap();
+
+    let uuid = random_uuid();
+    let session = game_session(&mock, uuid).await;
+    let tokens = session.tokens();
+    let identity = ClientIdentity::generate().unwrap();
+
+    let (endpoint, mut connection) = open(&server, &identity).await;
+    assert_eq!(connection.server_fingerprint(), Some(server.fingerprint()));
+
+    connection.handshake("bot", uuid, Some(&tokens.identity_token)).await.unwrap();
+    connection
+        .authenticate(session.client(), &tokens.identity_token, &tokens.session_token, identity.fingerprint())
+        .await
+        .unwrap();
+    connection.setup().await.unwrap();
+    assert_eq!(connection.phase(), Phase::Playing);
+
+    assert!(server.wait_for(EVENT_TIMEOUT, |e| *e == ServerEvent::JoinedWorld).await.is_some());
+    assert!(server.events().contains(&ServerEvent::Authenticated));
+
+    close(endpoint, connection).await;
+    let disconnected = server.wait_for(EVENT_TIMEOUT, |e| matches!(e, ServerEvent::ClientDisconnected { .. })).await;
+    assert_eq!(disconnected, Some(ServerEvent::ClientDisconnected { reason: "Fim do teste".to_string() }));
+}
+
+#[tokio::test]
+async fn insecure_handshake_joins_world() {
+    let server = MockGameServer::start(insecure()).await.unwrap();
+
+    let (endpoint, mut connection, result) = authenticate_insecure(&server, None).await;
+    result.unwrap();
+    connection.setup().await.unwrap();
+
+    assert!(server.wait_for(EVENT_TIMEOUT, |e| *e == ServerEvent::JoinedWorld).await.is_some());
+    close(endpoint, connection).await;
+}
+
+#[tokio::test]
+async fn insecure_connection_to_authenticated_server_fails() {
+    let server = MockGameServer::start(MockServerConfig::default()).await.unwrap();
+
+    let (_endpoint, connection, result) = authenticate_insecure(&server, None).await;
+    assert_eq!(result.unwrap_err().disconnect_kind(), Some(DisconnectKind::AuthFailed));
+    assert!(!connection.rejected_as_outdated());
+}
+
+#[tokio::test]
+async fn auth_token_without_server_grant_fails_as_auth() {
+    let mock = MockSessionService::start().await.unwrap();
+    let server = MockGameServer::start(MockServerConfig {
+        authority: Some(mock.authority()),
+        server_identity: false,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let uuid = random_uuid();
+    let session = game_session(&mock, uuid).await;
+    let tokens = session.tokens();
+    let identity = ClientIdentity::generate().unwrap();
+
+    // Sem identity token do servidor o AuthToken sai sem grant, como no log do servidor real
+    let (_endpoint, mut connection) = open(&server, &identity).await;
+    connection.handshake("bot", uuid, Some(&tokens.identity_token)).await.unwrap();
+    let result = connection
+        .authenticate(session.client(), &tokens.identity_token, &tokens.session_token, identity.fingerprint())
+        .await;
+
+    assert_eq!(result.unwrap_err().disconnect_kind(), Some(DisconnectKind::AuthFailed));
+    assert_eq!(
+        connection.last_disconnect().map(|d| d.reason_text()),
+        Some("Mutual authentication required - please update your client")
+    );
+    assert!(!connection.rejected_as_outdated());
+}
+
+#[tokio::test]
+async fn hostname_falls_back_across_resolved_addresses() {
+    let server = MockGameServer::start(insecure()).await.unwrap();
+    let identity = ClientIdentity::generate().unwrap();
+
+    // `localhost` pode resolver para ::1 antes de 127.0.0.1, onde o mock escuta
+    let address: ServerAddress = format!("localhost:{}", server.addr().port()).parse().unwrap();
+    let trust = ServerTrust::Pinned(server.fingerprint().to_string());
+    let config = net::configure_client(&identity, &address, trust, &TransportOptions::default()).unwrap();
+
+    let (endpoint, quic) = net::connect(&config, &address).await.unwrap();
+    assert_eq!(quic.remote_address(), server.addr());
+    quic.close(0u32.into(), b"");
+    endpoint.wait_idle().await;
+}
+
+// ============================================================================
+// Ping
+// ============================================================================
+
+#[tokio::test]
+async fn pings_are_answered_with_three_pongs() {
+    let mut config = insecure();
+    config.on(Stage::Setup, Action::Ping(7));
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (endpoint, mut connection, result) = authenticate_insecure(&server, None).await;
+    result.unwrap();
+    connection.setup().await.unwrap();
+
+    for pong_type in [PongType::Raw, PongType::Direct, PongType::Tick] {
+        let pong = ServerEvent::Pong { id: 7, pong_type };
+        assert!(server.wait_for(EVENT_TIMEOUT, |e| *e == pong).await.is_some(), "{:?}", pong);
+    }
+    assert_eq!(connection.latency().pings_answered, 1);
+
+    close(endpoint, connection).await;
+}
+
+// ============================================================================
+// Disconnect
+// ============================================================================
+
+async fn disconnected_with(reason: &str) -> (Connection, ProtocolError) {
+    let mut config = insecure();
+    config.on(Stage::Connected, Action::Disconnect(reason.to_string()));
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (_endpoint, connection, result) = authenticate_insecure(&server, None).await;
+    (connection, result.unwrap_err())
+}
+
+#[tokio::test]
+async fn server_disconnect_is_classified() {
+    let (connection, error) = disconnected_with("Server is full").await;
+    assert_eq!(error.disconnect_kind(), Some(DisconnectKind::ServerFull));
+    assert_eq!(connection.last_disconnect().map(|d| d.reason_text()), Some("Server is full"));
+    assert!(!connection.rejected_as_outdated());
+
+    let (_, error) = disconnected_with("You are banned").await;
+    assert_eq!(error.disconnect_kind(), Some(DisconnectKind::Banned));
+}
+
+#[tokio::test]
+async fn wrong_protocol_hash_is_rejected_as_outdated() {
+    let server = MockGameServer::start(MockServerConfig { protocol_hash: "0".repeat(64), ..insecure() })
+        .await
+        .unwrap();
+
+    let (_endpoint, connection, result) = authenticate_insecure(&server, None).await;
+    assert_eq!(result.unwrap_err().disconnect_kind(), Some(DisconnectKind::OutdatedClient));
+    assert!(connection.rejected_as_outdated());
+}
+
+// ============================================================================
+// Senha
+// ============================================================================
+
+#[tokio::test]
+async fn correct_password_is_accepted() {
+    let server = MockGameServer::start(MockServerConfig { password: Some("senha".to_string()), ..insecure() })
+        .await
+        .unwrap();
+
+    let (endpoint, mut connection, result) = authenticate_insecure(&server, Some("senha")).await;
+    result.unwrap();
+    assert!(server.events().contains(&ServerEvent::PasswordAttempt { accepted: true }));
+
+    connection.setup().await.unwrap();
+    close(endpoint, connection).await;
+}
+
+#[tokio::test]
+async fn wrong_password_is_rejected() {
+    let server = MockGameServer::start(MockServerConfig { password: Some("senha".to_string()), ..insecure() })
+        .await
+        .unwrap();
+
+    let (_endpoint, _connection, result) = authenticate_insecure(&server, Some("errada")).await;
+    assert!(matches!(result, Err(ProtocolError::PasswordRejected { attempts_remaining: 2 })), "{:?}", result);
+    assert!(server.events().contains(&ServerEvent::PasswordAttempt { accepted: false }));
+}
+
+#[tokio::test]
+async fn missing_password_fails_before_responding() {
+    let server = MockGameServer::start(MockServerConfig { password: Some("senha".to_string()), ..insecure() })
+        .await
+        .unwrap();
+
+    let (_endpoint, _connection, result) = authenticate_insecure(&server, None).await;
+    assert!(matches!(result, Err(ProtocolError::PasswordRequired)), "{:?}", result);
+}
+
+// ============================================================================
+// Pacotes inválidos e timeouts
+// ============================================================================
+
+#[tokio::test]
+async fn unknown_packet_id_is_ignored() {
+    let mut config = insecure();
+    config.on(Stage::Authenticating, Action::Raw { packet_id: 9999, payload: vec![1, 2, 3] });
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (endpoint, mut connection, result) = authenticate_insecure(&server, None).await;
+    result.unwrap();
+    connection.setup().await.unwrap();
+    close(endpoint, connection).await;
+}
+
+#[tokio::test]
+async fn malformed_packet_fails_the_phase() {
+    let mut config = insecure();
+    // nullBits diz que há desafio, mas o campo não veio
+    config.on(Stage::Authenticating, Action::Raw { packet_id: PACKET_CONNECT_ACCEPT, payload: vec![1] });
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (_endpoint, _connection, result) = authenticate_insecure(&server, None).await;
+    assert!(
+        matches!(result, Err(ProtocolError::Malformed { packet_id: PACKET_CONNECT_ACCEPT })),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn corrupt_compressed_packet_fails_the_phase() {
+    let mut config = insecure();
+    config.on(Stage::Setup, Action::Raw { packet_id: PACKET_WORLD_SETTINGS, payload: vec![1, 2, 3] });
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (_endpoint, mut connection, result) = authenticate_insecure(&server, None).await;
+    result.unwrap();
+    let setup = connection.setup().await.map(|_| ());
+    assert!(
+        matches!(setup, Err(ProtocolError::Decompress { packet_id: PACKET_WORLD_SETTINGS, .. })),
+        "{:?}",
+        setup
+    );
+}
+
+#[tokio::test]
+async fn stalled_server_hits_phase_timeout() {
+    let mut config = insecure();
+    config.on(Stage::Connected, Action::Stall(protocol::READ_TIMEOUT + Duration::from_secs(5)));
+    let server = MockGameServer::start(config).await.unwrap();
+
+    let (_endpoint, _connection, result) = authenticate_insecure(&server, None).await;
+    assert!(
+        matches!(result, Err(ProtocolError::Timeout { phase: Phase::Authenticating })),
+        "{:?}",
+        result
+    );
+}